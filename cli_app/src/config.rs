@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use ksp_cfg_formatter::{parser::DEFAULT_MAX_LINE_LENGTH, Formatter, Indentation, LineReturn};
+use serde::Deserialize;
+
+use crate::Args;
+
+/// Name of the file holding the project level settings
+const CONFIG_FILE_NAME: &str = ".ksp-fmt.toml";
+
+/// Project level settings, read from a `.ksp-fmt.toml` file. Settings passed on the command line take precedence
+///
+/// Sorting keys is not supported, since the order of keys matters to KSP and Module Manager, for example for repeated keys
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Number of spaces used for indentation. Tabs are used if not set
+    pub indentation: Option<usize>,
+    /// Collapses blocks that only take up one line and are short enough
+    pub inline: Option<bool>,
+    /// Line endings to use. Identified from the text if not set
+    pub line_endings: Option<LineEndings>,
+    /// Length of the longest line a block can be collapsed to
+    pub collapse_length: Option<usize>,
+}
+
+/// Line endings as written in the config file, or passed with `--line-endings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndings {
+    Lf,
+    Crlf,
+    Identify,
+}
+
+impl From<LineEndings> for LineReturn {
+    fn from(value: LineEndings) -> Self {
+        match value {
+            LineEndings::Lf => Self::LF,
+            LineEndings::Crlf => Self::CRLF,
+            LineEndings::Identify => Self::Identify,
+        }
+    }
+}
+
+impl Config {
+    /// Finds and reads the config file for the file at `path`, or for the current directory if no path is provided.
+    ///
+    /// The directory of the file and all its parents are searched, and the closest config file is used.
+    /// If no config file is found, the default config is returned
+    pub fn for_path(path: Option<&Path>) -> anyhow::Result<Self> {
+        let start = match path {
+            Some(path) => path
+                .canonicalize()
+                .with_context(|| format!("Failed to find {}", path.display()))?,
+            None => std::env::current_dir()?,
+        };
+        match find_config_file(&start) {
+            Some(config_path) => Self::read(&config_path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Reads the config from the file at `path`
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config in {}", path.display()))
+    }
+
+    /// Combines the config with the settings from `args`, which take precedence
+    pub fn with_args(&self, args: &Args) -> Self {
+        Self {
+            indentation: args.indentation.or(self.indentation),
+            inline: args.inline.or(self.inline),
+            line_endings: args.line_endings.or(self.line_endings),
+            collapse_length: self.collapse_length,
+        }
+    }
+
+    /// Creates a `Formatter` from the config, with the settings from `args` taking precedence
+    pub fn formatter(&self, args: &Args) -> Formatter {
+        let config = self.with_args(args);
+        let indentation = Indentation::new(
+            config.indentation.is_some(),
+            config.indentation.unwrap_or_default(),
+        );
+        let line_return = config
+            .line_endings
+            .map_or(LineReturn::Identify, LineReturn::from);
+        Formatter::new(indentation, config.inline, line_return)
+            .max_line_length(config.collapse_length.unwrap_or(DEFAULT_MAX_LINE_LENGTH))
+    }
+}
+
+/// Prints the settings in effect, one `key = value` per line, with the defaults filled in
+impl Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.indentation {
+            Some(spaces) => writeln!(f, "indentation = {spaces}")?,
+            None => writeln!(f, "indentation = tabs")?,
+        }
+        match self.inline {
+            Some(inline) => writeln!(f, "inline = {inline}")?,
+            None => writeln!(f, "inline = keep")?,
+        }
+        let line_endings = match self.line_endings.unwrap_or(LineEndings::Identify) {
+            LineEndings::Lf => "lf",
+            LineEndings::Crlf => "crlf",
+            LineEndings::Identify => "identify",
+        };
+        writeln!(f, "line_endings = {line_endings}")?;
+        writeln!(
+            f,
+            "collapse_length = {}",
+            self.collapse_length.unwrap_or(DEFAULT_MAX_LINE_LENGTH)
+        )
+    }
+}
+
+/// Configs for the directories of the files being formatted, so the config file is only searched for and read once per directory
+#[derive(Debug, Default)]
+pub struct ConfigCache {
+    configs: HashMap<PathBuf, Config>,
+}
+
+impl ConfigCache {
+    /// Returns the config for the file at `path`. See [`Config::for_path`]
+    pub fn for_path(&mut self, path: &Path) -> anyhow::Result<Config> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Failed to find {}", path.display()))?;
+        let dir = path.parent().unwrap_or(&path).to_owned();
+        if let Some(config) = self.configs.get(&dir) {
+            return Ok(config.clone());
+        }
+        let config = match find_config_file(&dir) {
+            Some(config_path) => Config::read(&config_path)?,
+            None => Config::default(),
+        };
+        self.configs.insert(dir, config.clone());
+        Ok(config)
+    }
+}
+
+/// Looks for the config file in `start` and all of its parents, returning the closest one
+fn find_config_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn config_in_parent_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(CONFIG_FILE_NAME),
+            "indentation = 2\ninline = false\nline_endings = \"crlf\"\ncollapse_length = 10\n",
+        )
+        .unwrap();
+        let sub_dir = dir.path().join("GameData").join("Mod");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        let file = sub_dir.join("patch.cfg");
+        std::fs::write(&file, "node { key = val }\n").unwrap();
+
+        let config = Config::for_path(Some(&file)).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                indentation: Some(2),
+                inline: Some(false),
+                line_endings: Some(LineEndings::Crlf),
+                collapse_length: Some(10),
+            }
+        );
+        let args = Args::parse_from(["ksp-cfg-formatter"]);
+        let text = std::fs::read_to_string(&file).unwrap();
+        assert_eq!(
+            config.formatter(&args).format_text(&text).unwrap(),
+            "node\r\n{\r\n  key = val\r\n}\r\n"
+        );
+        // Arguments take precedence over the config file
+        let args = Args::parse_from(["ksp-cfg-formatter", "--indentation", "4"]);
+        assert_eq!(
+            config.formatter(&args).format_text(&text).unwrap(),
+            "node\r\n{\r\n    key = val\r\n}\r\n"
+        );
+        let args = Args::parse_from(["ksp-cfg-formatter", "--line-endings", "lf"]);
+        assert_eq!(
+            config.formatter(&args).format_text(&text).unwrap(),
+            "node\n{\n  key = val\n}\n"
+        );
+    }
+
+    #[test]
+    fn collapse_length() {
+        let config = Config {
+            inline: Some(true),
+            collapse_length: Some(12),
+            ..Config::default()
+        };
+        let args = Args::parse_from(["ksp-cfg-formatter"]);
+        let formatter = config.formatter(&args);
+        assert_eq!(
+            formatter.format_text("a { k = v }\n").unwrap(),
+            "a { k = v }\n"
+        );
+        assert_eq!(
+            formatter.format_text("node { key = val }\n").unwrap(),
+            "node\n{\n\tkey = val\n}\n"
+        );
+    }
+
+    #[test]
+    fn cached_per_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&config_file, "indentation = 2\n").unwrap();
+        let first = dir.path().join("first.cfg");
+        let second = dir.path().join("second.cfg");
+        std::fs::write(&first, "").unwrap();
+        std::fs::write(&second, "").unwrap();
+
+        let mut cache = ConfigCache::default();
+        assert_eq!(cache.for_path(&first).unwrap().indentation, Some(2));
+        // The config file is not read again for files in the same directory
+        std::fs::write(&config_file, "indentation = 4\n").unwrap();
+        assert_eq!(cache.for_path(&second).unwrap().indentation, Some(2));
+        assert_eq!(
+            ConfigCache::default()
+                .for_path(&second)
+                .unwrap()
+                .indentation,
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn invalid_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(CONFIG_FILE_NAME), "indentation = \"two\"\n").unwrap();
+        let file = dir.path().join("patch.cfg");
+        std::fs::write(&file, "").unwrap();
+        let err = ConfigCache::default().for_path(&file).unwrap_err();
+        assert!(format!("{err:#}").contains("Invalid config"), "{err:#}");
+    }
+
+    #[test]
+    fn no_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(find_config_file(dir.path()), None);
+    }
+}
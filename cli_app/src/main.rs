@@ -1,14 +1,19 @@
-use clap::Parser;
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use config::{Config, ConfigCache};
 use itertools::Itertools;
-use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use ksp_cfg_formatter::parser::{Range, Severity};
+use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::{
     fs::{self, metadata},
     io::BufRead,
+    path::Path,
     result::Result,
 };
 use walkdir::WalkDir;
 
+mod config;
+
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Parser, Debug, Clone)]
 #[command(author, about, long_about = None)]
@@ -20,6 +25,9 @@ If no path is provided, text is read from stdin."
     )]
     path: Option<String>,
 
+    #[arg(help = "More files or folders to format, in addition to --path")]
+    paths: Vec<String>,
+
     #[arg(
         long,
         help = "Collapses blocks that only take up one line and are short enough"
@@ -32,6 +40,13 @@ If no path is provided, text is read from stdin."
     )]
     indentation: Option<usize>,
 
+    #[arg(
+        long,
+        value_enum,
+        help = "Line endings to use. Identified from the text if not set"
+    )]
+    line_endings: Option<config::LineEndings>,
+
     #[arg(
         long,
         help = "Prints output to stdout instead of writing back to file when reading from path"
@@ -40,10 +55,19 @@ If no path is provided, text is read from stdin."
 
     #[arg(
         long,
-        help = "Parser only checks the file for errors, without formatting it"
+        help = "Checks the files for issues and formatting, without changing them.
+Exits with 1 if any issues are found, or 2 if the only problem is files that are not formatted"
     )]
     check: bool,
 
+    #[arg(
+        long,
+        conflicts_with = "check",
+        help = "Only checks that the files are already formatted, meaning formatting them again gives the same text, without reporting other issues.
+Exits with 1 if any file can't be parsed, or 2 if any file is not formatted"
+    )]
+    check_idempotent: bool,
+
     #[arg(
         long,
         help = "Allow parsing to be lossy, replacing invalid chars with �"
@@ -52,6 +76,122 @@ If no path is provided, text is read from stdin."
 
     #[arg(long, help = "Only check files inside a GameData folder")]
     only_in_gamedata: bool,
+
+    #[arg(
+        long,
+        overrides_with = "only_in_gamedata",
+        help = "Include all cfg files. Overrides an earlier --only-in-gamedata, and is overridden by a later one"
+    )]
+    all: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SeverityLevel::Warning,
+        help = "Least severe issues to report when checking files"
+    )]
+    severity: SeverityLevel,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Human,
+        help = "How issues are printed when checking. `github` prints GitHub Actions annotations, shown inline in pull requests"
+    )]
+    format: OutputFormat,
+
+    #[arg(
+        long,
+        help = "Don't print a summary of the formatted files. When checking, nothing is printed, and only the exit code is set"
+    )]
+    quiet: bool,
+
+    #[arg(
+        long,
+        help = "Prints the settings in effect, from the config file and the arguments, and exits"
+    )]
+    print_config: bool,
+}
+
+/// Outcome of formatting a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileStatus {
+    /// The file was already formatted
+    Unchanged,
+    /// The formatted text differs from the file
+    Changed,
+    /// The file could not be parsed, and was left as is
+    Errored,
+}
+
+/// Summarizes the outcome of formatting several files, like `Formatted 12 files, 3 changed, 0 errors`
+fn summary(statuses: &[FileStatus]) -> String {
+    let count = |status| statuses.iter().filter(|s| **s == status).count();
+    let plural =
+        |count: usize, noun: &str| format!("{count} {noun}{}", if count == 1 { "" } else { "s" });
+    format!(
+        "Formatted {}, {} changed, {}",
+        plural(statuses.len(), "file"),
+        count(FileStatus::Changed),
+        plural(count(FileStatus::Errored), "error")
+    )
+}
+
+/// Severity levels that can be selected on the command line
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum SeverityLevel {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl From<SeverityLevel> for Severity {
+    fn from(level: SeverityLevel) -> Self {
+        match level {
+            SeverityLevel::Error => Self::Error,
+            SeverityLevel::Warning => Self::Warning,
+            SeverityLevel::Info => Self::Info,
+            SeverityLevel::Hint => Self::Hint,
+        }
+    }
+}
+
+/// Formats that issues can be printed in when checking files
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Human,
+    Github,
+}
+
+/// Formats an issue as a GitHub Actions workflow command, like `::error file={path},line={l},col={c}::{message}`.
+/// Without a range, the annotation is for the whole file
+fn github_annotation(
+    severity: &Severity,
+    path: &str,
+    range: Option<Range>,
+    message: &str,
+) -> String {
+    // Special characters have to be escaped, with `:` and `,` only being special in properties
+    let escape_data = |s: &str| {
+        s.replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    let escape_property = |s: &str| escape_data(s).replace(':', "%3A").replace(',', "%2C");
+    let command = match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info | Severity::Hint => "notice",
+    };
+    let position = range.map_or_else(String::new, |range| {
+        format!(",line={},col={}", range.start.line, range.start.col)
+    });
+    format!(
+        "::{command} file={}{position}::{}",
+        escape_property(path),
+        escape_data(message)
+    )
 }
 
 fn main() {
@@ -64,13 +204,83 @@ fn main() {
     let args = Args::parse();
 
     // Read input from either a path or stdin if no path is provided
-    if let Some(path) = &args.path {
-        let paths = files_from_path(path, args.only_in_gamedata);
-        let res: Vec<String> = paths
+    let input_paths = args.path.iter().chain(&args.paths).collect_vec();
+    if args.print_config {
+        // Settings are resolved for the first path, or the current directory
+        let config = Config::for_path(input_paths.first().map(Path::new))
+            .unwrap_or_else(|err| exit_with_error(&err));
+        print!("{}", config.with_args(&args));
+        return;
+    }
+    if !input_paths.is_empty() {
+        let paths = input_paths
+            .into_iter()
+            .map(|path| files_from_path(path, args.only_in_gamedata))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap_or_else(|err| exit_with_error(&err))
+            .into_iter()
+            .flatten()
+            .unique()
+            .collect_vec();
+        // Configs are read up front, so an invalid config file stops everything before any file is changed
+        let mut cache = ConfigCache::default();
+        let configs = paths
+            .iter()
+            .map(|path| cache.for_path(Path::new(path)))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap_or_else(|err| exit_with_error(&err));
+        let (res, statuses): (Vec<Vec<String>>, Vec<Option<FileStatus>>) = paths
             .par_iter()
-            .flat_map(|path| worker_task(&args, path))
-            .collect();
-        println!("{}", res.iter().format("\n\n\n"));
+            .zip(&configs)
+            .map(|(path, config)| worker_task(&args, path, config))
+            .unzip();
+        let res = res.into_iter().flatten().collect_vec();
+        if args.check || args.check_idempotent {
+            let unformatted = paths
+                .iter()
+                .zip(&statuses)
+                .filter(|(_, status)| **status == Some(FileStatus::Changed))
+                .map(|(path, _)| path)
+                .collect_vec();
+            if !args.quiet {
+                match args.format {
+                    OutputFormat::Human => {
+                        println!("{}", res.iter().format("\n\n\n"));
+                        for path in &unformatted {
+                            println!("{path}\nfile is not formatted");
+                        }
+                    }
+                    OutputFormat::Github => {
+                        for line in &res {
+                            println!("{line}");
+                        }
+                        for path in &unformatted {
+                            println!(
+                                "{}",
+                                github_annotation(
+                                    &Severity::Error,
+                                    path,
+                                    None,
+                                    "file is not formatted"
+                                )
+                            );
+                        }
+                    }
+                }
+            }
+            // Issues in the files are more important than the formatting
+            if !res.is_empty() {
+                std::process::exit(1);
+            }
+            if !unformatted.is_empty() {
+                std::process::exit(2);
+            }
+        } else {
+            println!("{}", res.iter().format("\n\n\n"));
+            if !args.quiet {
+                eprintln!("{}", summary(&statuses.into_iter().flatten().collect_vec()));
+            }
+        }
     } else {
         let mut text: String = String::new();
         // Collect multi-line input from stdin
@@ -79,84 +289,196 @@ fn main() {
             text.push_str(&line);
             text.push('\n');
         }
-        format_file(&args, &text, args.path.clone());
+        let config = Config::for_path(None).unwrap_or_else(|err| exit_with_error(&err));
+        format_file(&args, &text, None, &config);
     }
 }
 
-fn worker_task(args: &Args, path: &String) -> Vec<String> {
+/// Prints the error and exits, for errors that keep any file from being formatted, like an invalid config file
+fn exit_with_error(err: &anyhow::Error) -> ! {
+    eprintln!("{err:#}");
+    std::process::exit(1)
+}
+
+/// Checks or formats the file at `path`. Returns the issues found when checking, and the outcome of formatting the file
+fn worker_task(args: &Args, path: &String, config: &Config) -> (Vec<String>, Option<FileStatus>) {
     let mut res = vec![];
     let text = if args.lossy {
-        let raw = fs::read(path).map_or_else(|err| panic!("{err}"), |t| t);
+        let raw = fs::read(path).unwrap_or_else(|err| panic!("{err}"));
         String::from_utf8_lossy(&raw).to_string()
     } else {
-        fs::read_to_string(path).map_or_else(|_| panic!("Failed to read text from {path}"), |t| t)
+        fs::read_to_string(path).unwrap_or_else(|_| panic!("Failed to read text from {path}"))
     };
-    if args.check {
-        match ksp_cfg_formatter::parse_to_ast(&text) {
-            Ok(doc) => match ksp_cfg_formatter::transformer::assignments_first(doc) {
-                Ok(_) => (),
-                Err(_err) => {
-                    // res.push(format!("{path}\n{_err}"))
-                }
-            },
-            Err(errs) => {
-                // res.push(format!("{path}"));
-                use ksp_cfg_formatter::parser::Severity as sev;
-                for err in errs.0 {
-                    res.push(format!("{} {}\n{}", path, err.range, err));
-                }
-                for diag in errs.1 {
-                    if matches!(diag.severity, Some(sev::Error | sev::Warning)) {
-                        res.push(format!("{} {}\n{}", path, diag.range, diag.message));
+    if args.check_idempotent {
+        // Uses the same settings as formatting the file, so only the formatting is compared
+        let (_output, status) = format_text(args, &text, config);
+        if status == FileStatus::Errored {
+            let message = "file could not be parsed, so it can't be formatted";
+            res.push(match args.format {
+                OutputFormat::Human => format!("{path}\n{message}"),
+                OutputFormat::Github => github_annotation(&Severity::Error, path, None, message),
+            });
+        }
+        (res, Some(status))
+    } else if args.check {
+        let level = Severity::from(args.severity);
+        let mut push_error = |err: &ksp_cfg_formatter::parser::Error| {
+            if err.severity <= level {
+                res.push(match args.format {
+                    OutputFormat::Human => format!("{path}\n{}", err.render(&text).trim_end()),
+                    OutputFormat::Github => {
+                        github_annotation(&err.severity, path, Some(err.range), &err.message)
                     }
-                }
+                });
             }
         };
+        // Unbalanced brackets are reported on their own, as they can cause confusing errors from the parser
+        let brackets = ksp_cfg_formatter::unbalanced_brackets(&text);
+        brackets.iter().for_each(&mut push_error);
+        if brackets.is_empty() {
+            match ksp_cfg_formatter::parse_to_ast(&text) {
+                Ok(doc) => match ksp_cfg_formatter::transformer::assignments_first(doc) {
+                    Ok(_) => (),
+                    Err(_err) => {
+                        // res.push(format!("{path}\n{_err}"))
+                    }
+                },
+                Err(errs) => {
+                    errs.0.iter().for_each(&mut push_error);
+                    for diag in errs.1 {
+                        if let Some(severity) = diag.severity.as_ref().filter(|sev| **sev <= level)
+                        {
+                            res.push(match args.format {
+                                OutputFormat::Human => {
+                                    format!("{} {}\n{severity}: {}", path, diag.range, diag.message)
+                                }
+                                OutputFormat::Github => github_annotation(
+                                    severity,
+                                    path,
+                                    Some(diag.range),
+                                    &diag.message,
+                                ),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        let (_output, status) = format_text(args, &text, config);
+        (res, Some(status))
     } else {
-        format_file(args, &text, Some(path.clone()));
+        (
+            res,
+            Some(format_file(args, &text, Some(path.clone()), config)),
+        )
+    }
+}
+
+/// Formats the text with the settings from `config` and `args`. If the text can't be formatted, it is returned unchanged
+fn format_text(args: &Args, text: &str, config: &Config) -> (String, FileStatus) {
+    let formatter = config.formatter(args);
+    match formatter.format_text(text) {
+        Ok(output) if output == text => (output, FileStatus::Unchanged),
+        Ok(output) => (output, FileStatus::Changed),
+        Err(_) => (text.to_owned(), FileStatus::Errored),
     }
-    res
 }
 
-fn format_file(args: &Args, text: &str, path: Option<String>) {
-    // Set up formatter and use it to format the text
-    let indentaion = Indentation::from(args.indentation);
-    let formatter = Formatter::new(indentaion, args.inline, LineReturn::Identify);
-    let output = formatter.fail_silent().format_text(text).unwrap();
+fn format_file(args: &Args, text: &str, path: Option<String>, config: &Config) -> FileStatus {
+    let (output, status) = format_text(args, text, config);
 
     // write output to path or stdout
     match (args.stdout, path) {
         (false, Some(path)) => {
-            let _res = fs::write(path, output);
+            if status == FileStatus::Changed {
+                let _res = fs::write(path, output);
+            }
         }
         _ => {
             print!("{output}");
         }
     }
+    status
 }
 
 /// Generates a Vec of all the paths to ksp cfg files. if bool is set, only return files in a `GameData` folder
-fn files_from_path(path: &String, only_in_gamedata: bool) -> Vec<String> {
-    let mut paths = Vec::new();
-    if metadata(path).unwrap().is_file() {
-        paths.push(path.clone());
+fn files_from_path(path: &String, only_in_gamedata: bool) -> anyhow::Result<Vec<String>> {
+    if metadata(path)
+        .with_context(|| format!("Failed to find {path}"))?
+        .is_file()
+    {
+        return Ok(vec![path.clone()]);
     }
+    let mut paths = Vec::new();
     for path in WalkDir::new(path).into_iter().filter_map(Result::ok) {
         let name = path.path().to_owned();
         if let Some(extension) = name.extension() {
-            if extension == "cfg"
-                && (!only_in_gamedata
-                    || name
-                        .canonicalize()
-                        .unwrap()
-                        .ancestors()
-                        .any(|n| n.ends_with("GameData")))
-            {
+            if extension == "cfg" && (!only_in_gamedata || is_in_gamedata(&name)) {
                 if let Some(name) = name.to_str() {
                     paths.push(name.to_owned());
                 };
             }
         }
     }
-    paths
+    Ok(paths)
+}
+
+/// Checks if any of the parent folders of the path is named `GameData`, ignoring case.
+///
+/// Only the folder names are compared, so verbatim `\\?\` prefixes added by `canonicalize` on Windows don't matter
+fn is_in_gamedata(path: &Path) -> bool {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+    path.ancestors().any(|ancestor| {
+        ancestor
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.eq_ignore_ascii_case("GameData"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_gamedata_flag_wins() {
+        let args = Args::parse_from(["ksp-cfg-formatter", "--only-in-gamedata", "--all"]);
+        assert!(!args.only_in_gamedata);
+        let args = Args::parse_from(["ksp-cfg-formatter", "--all", "--only-in-gamedata"]);
+        assert!(args.only_in_gamedata);
+    }
+
+    #[test]
+    fn gamedata_is_case_insensitive() {
+        let dir = tempfile::tempdir().unwrap();
+        let mod_dir = dir.path().join("gamedata").join("MyMod");
+        std::fs::create_dir_all(&mod_dir).unwrap();
+        let file = mod_dir.join("patch.cfg");
+        std::fs::write(&file, "node {}\n").unwrap();
+        let other = dir.path().join("other.cfg");
+        std::fs::write(&other, "node {}\n").unwrap();
+
+        assert!(is_in_gamedata(&file));
+        assert!(is_in_gamedata(
+            &dir.path().join("GAMEDATA").join("missing.cfg")
+        ));
+        assert!(!is_in_gamedata(&other));
+
+        let root = dir.path().to_str().unwrap().to_owned();
+        assert_eq!(files_from_path(&root, true).unwrap().len(), 1);
+        assert_eq!(files_from_path(&root, false).unwrap().len(), 2);
+        assert!(files_from_path(&format!("{root}/missing"), false).is_err());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn gamedata_with_verbatim_prefix() {
+        assert!(is_in_gamedata(Path::new(
+            r"\\?\C:\KSP\GameData\MyMod\patch.cfg"
+        )));
+        assert!(is_in_gamedata(Path::new(
+            r"\\?\UNC\server\share\gamedata\patch.cfg"
+        )));
+        assert!(!is_in_gamedata(Path::new(r"\\?\C:\KSP\Ships\patch.cfg")));
+    }
 }
@@ -0,0 +1,203 @@
+use std::process::Command;
+
+fn check(dir: &std::path::Path, extra_args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_ksp-cfg-formatter-cli"))
+        .arg("--check")
+        .arg("--path")
+        .arg(dir)
+        .args(extra_args)
+        .output()
+        .expect("failed to run the formatter")
+}
+
+#[test]
+fn severity_filter() {
+    let dir = tempfile::tempdir().unwrap();
+    // An operator in a node without an operator is a warning
+    std::fs::write(dir.path().join("warning.cfg"), "PART\n{\n\t@key = val\n}\n").unwrap();
+
+    let output = check(dir.path(), &[]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("warning.cfg"));
+    assert!(stdout.contains("\nwarning: "), "{stdout}");
+
+    let output = check(dir.path(), &["--severity", "error"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "");
+}
+
+#[test]
+fn multiple_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file.cfg");
+    std::fs::write(&file, "a {   key   =   val }\n").unwrap();
+    let sub_dir = dir.path().join("sub");
+    std::fs::create_dir(&sub_dir).unwrap();
+    std::fs::write(sub_dir.join("other.cfg"), "b {   key   =   val }\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ksp-cfg-formatter-cli"))
+        .arg(&file)
+        .arg(&sub_dir)
+        .output()
+        .expect("failed to run the formatter");
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "a { key = val }\n");
+    assert_eq!(
+        std::fs::read_to_string(sub_dir.join("other.cfg")).unwrap(),
+        "b { key = val }\n"
+    );
+}
+
+#[test]
+fn format_summary() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("clean.cfg"), "a { key = val }\n").unwrap();
+    std::fs::write(dir.path().join("dirty.cfg"), "b {   key   =   val }\n").unwrap();
+    std::fs::write(dir.path().join("broken.cfg"), "c {\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ksp-cfg-formatter-cli"))
+        .arg(dir.path())
+        .output()
+        .expect("failed to run the formatter");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "Formatted 3 files, 1 changed, 1 error"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("broken.cfg")).unwrap(),
+        "c {\n"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ksp-cfg-formatter-cli"))
+        .arg(dir.path())
+        .arg("--quiet")
+        .output()
+        .expect("failed to run the formatter");
+    assert_eq!(String::from_utf8_lossy(&output.stderr).trim(), "");
+}
+
+#[test]
+fn print_config() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join(".ksp-fmt.toml"),
+        "indentation = 2\nline_endings = \"crlf\"\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ksp-cfg-formatter-cli"))
+        .current_dir(dir.path())
+        .args(["--print-config", "--indentation", "4"])
+        .output()
+        .expect("failed to run the formatter");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "indentation = 4\ninline = keep\nline_endings = crlf\ncollapse_length = 72\n"
+    );
+}
+
+#[test]
+fn invalid_config() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join(".ksp-fmt.toml"), "indentation = \"two\"\n").unwrap();
+    let file = dir.path().join("file.cfg");
+    std::fs::write(&file, "a {   key   =   val }\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ksp-cfg-formatter-cli"))
+        .arg(dir.path())
+        .output()
+        .expect("failed to run the formatter");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid config"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "a {   key   =   val }\n"
+    );
+}
+
+#[test]
+fn check_formatting() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("indented.cfg");
+    let text = "PART\n{\n    key = val\n}\n";
+    std::fs::write(&file, text).unwrap();
+
+    let output = check(dir.path(), &[]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("indented.cfg\nfile is not formatted"),
+        "{stdout}"
+    );
+    // The file is not changed
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), text);
+
+    // Only the exit code is set in quiet mode
+    let output = check(dir.path(), &["--quiet"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+
+    // Issues take precedence over formatting
+    std::fs::write(dir.path().join("broken.cfg"), "PART {\n").unwrap();
+    let output = check(dir.path(), &["--quiet"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+
+    std::fs::write(dir.path().join("broken.cfg"), "PART {}\n").unwrap();
+    std::fs::write(&file, "PART\n{\n\tkey = val\n}\n").unwrap();
+    assert_eq!(check(dir.path(), &["--quiet"]).status.code(), Some(0));
+}
+
+#[test]
+fn github_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("broken.cfg");
+    std::fs::write(&file, "PART\n{\n\tkey = val\n").unwrap();
+
+    let output = check(dir.path(), &["--format", "github"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let file = file.display().to_string().replace(':', "%3A");
+    assert_eq!(
+        stdout.trim_end(),
+        format!("::error file={file},line=2,col=1::`{{` is never closed by a `}}`")
+    );
+}
+
+#[test]
+fn check_idempotent() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("file.cfg");
+    let idempotent = || {
+        Command::new(env!("CARGO_BIN_EXE_ksp-cfg-formatter-cli"))
+            .arg("--check-idempotent")
+            .arg(dir.path())
+            .output()
+            .expect("failed to run the formatter")
+    };
+    // Lint issues are not reported, only the formatting is checked
+    std::fs::write(&file, "PART\n{\n\t@key = val\n}\n").unwrap();
+    let output = idempotent();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "");
+
+    std::fs::write(&file, "PART\n{\n  key = val\n}\n").unwrap();
+    let output = idempotent();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("file is not formatted"));
+
+    // The configured indentation is used when checking, like when formatting
+    std::fs::write(dir.path().join(".ksp-fmt.toml"), "indentation = 2\n").unwrap();
+    assert_eq!(idempotent().status.code(), Some(0));
+
+    std::fs::write(&file, "PART {\n").unwrap();
+    let output = idempotent();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("could not be parsed"));
+}
@@ -29,7 +29,7 @@ fn sock_lint_only(c: &mut Criterion) {
     let text = std::fs::read_to_string(path).unwrap();
     let (document, _errs) = ksp_cfg_formatter::parser::parse(&text);
     c.bench_function("SOCK lint only", |b| {
-        b.iter(|| ksp_cfg_formatter::linter::lint_ast(black_box(&document), None))
+        b.iter(|| ksp_cfg_formatter::linter::lint_ast(black_box(&document), &text, None))
     });
 }
 
@@ -0,0 +1,208 @@
+//! The parsed AST borrows all text from the source it was parsed from, so it can not be built from owned strings directly.
+//! Instead, [`NodeBuilder`] owns the text, and [`NodeBuilder::build`] creates a regular [`Node`] borrowing from the builder.
+//! This way everything working on the parsed AST, like printing and the transformers, also works on built nodes.
+//!
+//! Example:
+//! ```
+//! use ksp_cfg_formatter::{
+//!     builder::NodeBuilder,
+//!     parser::{ASTPrint, Operator, PrintSettings},
+//! };
+//!
+//! let builder = NodeBuilder::new("PART")
+//!     .operator(Operator::Edit)
+//!     .name("foo")
+//!     .key_val("mass", "1");
+//! assert_eq!(
+//!     builder.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+//!     "@PART[foo] { mass = 1 }\n"
+//! );
+//! ```
+
+use crate::parser::{
+    ASTPrint, AssignmentOperator, KeyVal, Node, NodeItem, Operator, PrintSettings, Range, Ranged,
+};
+
+/// Builds a [`Node`] from owned strings. See the [module documentation](self)
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NodeBuilder {
+    operator: Option<Operator>,
+    identifier: String,
+    name: Option<String>,
+    items: Vec<BuilderItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BuilderItem {
+    KeyVal {
+        operator: Option<Operator>,
+        key: String,
+        assignment_operator: AssignmentOperator,
+        value: String,
+    },
+    Node(NodeBuilder),
+}
+
+impl NodeBuilder {
+    /// Creates a builder for a node with the identifier, like `PART`, and an empty block
+    #[must_use]
+    pub fn new(identifier: impl Into<String>) -> Self {
+        Self {
+            identifier: identifier.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the operator of the node, like `@` for [`Operator::Edit`]
+    #[must_use]
+    pub fn operator(self, operator: Operator) -> Self {
+        Self {
+            operator: Some(operator),
+            ..self
+        }
+    }
+
+    /// Sets the name of the node, printed as `PART[name]`
+    #[must_use]
+    pub fn name(self, name: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..self
+        }
+    }
+
+    /// Adds an assignment, `key = value`, to the end of the block
+    #[must_use]
+    pub fn key_val(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.key_val_with(None, key, AssignmentOperator::Assign, value)
+    }
+
+    /// Adds an assignment with an optional operator and any assignment operator, like `@key += value`, to the end of the block
+    #[must_use]
+    pub fn key_val_with(
+        mut self,
+        operator: Option<Operator>,
+        key: impl Into<String>,
+        assignment_operator: AssignmentOperator,
+        value: impl Into<String>,
+    ) -> Self {
+        self.items.push(BuilderItem::KeyVal {
+            operator,
+            key: key.into(),
+            assignment_operator,
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Adds a child node to the end of the block
+    #[must_use]
+    pub fn child(mut self, child: Self) -> Self {
+        self.items.push(BuilderItem::Node(child));
+        self
+    }
+
+    /// Builds a top level [`Node`], borrowing the text from the builder. All ranges in the node are empty
+    #[must_use]
+    pub fn build(&self) -> Node<'_> {
+        self.build_node(true)
+    }
+
+    fn build_node(&self, top_level: bool) -> Node<'_> {
+        let mut node = Node::default();
+        node.set_top_level(top_level);
+        node.operator = self.operator.map(ranged);
+        node.identifier = ranged(self.identifier.as_str().into());
+        node.name = self.name.as_deref().map(|name| ranged(vec![name]));
+        node.block = self
+            .items
+            .iter()
+            .map(|item| match item {
+                BuilderItem::KeyVal {
+                    operator,
+                    key,
+                    assignment_operator,
+                    value,
+                } => {
+                    let mut key_val = KeyVal::default();
+                    key_val.operator = (*operator).map(ranged);
+                    key_val.key = ranged(key.as_str());
+                    key_val.assignment_operator = ranged(*assignment_operator);
+                    key_val.val = ranged(value.as_str().into());
+                    NodeItem::KeyVal(ranged(key_val))
+                }
+                BuilderItem::Node(child) => NodeItem::Node(ranged(child.build_node(false))),
+            })
+            .collect();
+        node
+    }
+}
+
+impl ASTPrint for NodeBuilder {
+    fn ast_print(
+        &self,
+        depth: usize,
+        indentation: &str,
+        line_ending: &str,
+        settings: &PrintSettings,
+    ) -> String {
+        self.build()
+            .ast_print(depth, indentation, line_ending, settings)
+    }
+}
+
+/// Wraps an item built from code, which has no position in any text
+fn ranged<T>(inner: T) -> Ranged<T> {
+    Ranged::new(inner, Range::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::parser::{parse_node, AssignmentOperator, DocItem, Document, Operator};
+
+    use super::*;
+
+    #[test]
+    fn test_build_node() {
+        let builder = NodeBuilder::new("PART")
+            .operator(Operator::Edit)
+            .name("foo")
+            .key_val("mass", "1");
+        assert_eq!(
+            builder.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+            "@PART[foo] { mass = 1 }\n"
+        );
+        // The built node is the same as the parsed one
+        let parsed = parse_node("@PART[foo] { mass = 1 }").unwrap();
+        assert!(builder.build().eq_ignore_ranges(&parsed));
+    }
+
+    #[test]
+    fn test_build_nested() {
+        let builder = NodeBuilder::new("PART")
+            .operator(Operator::Edit)
+            .name("foo")
+            .key_val_with(
+                Some(Operator::Edit),
+                "mass",
+                AssignmentOperator::Multiply,
+                "2",
+            )
+            .child(NodeBuilder::new("MODULE").key_val("name", "ModuleEngines"));
+        let doc = Document {
+            statements: vec![DocItem::Node(ranged(builder.build()))],
+        };
+        let formatter =
+            crate::Formatter::new(crate::Indentation::Tabs, Some(false), crate::LineReturn::LF);
+        let expected =
+            "@PART[foo]\n{\n\t@mass *= 2\n\tMODULE\n\t{\n\t\tname = ModuleEngines\n\t}\n}\n";
+        assert_eq!(
+            doc.ast_print(0, "\t", "\n", &PrintSettings::new(Some(false))),
+            expected
+        );
+        // The printed text is formatted and can be parsed again
+        assert_eq!(formatter.format_text(expected).unwrap(), expected);
+    }
+}
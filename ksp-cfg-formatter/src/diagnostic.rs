@@ -0,0 +1,42 @@
+use std::fmt::Display;
+
+use crate::parser::{Range, Severity};
+
+/// Struct for a diagnostic message, like an error or warning
+#[derive(Debug, Default)]
+pub struct Diagnostic {
+    /// The text range the diagnostic covers
+    pub range: Range,
+    /// The severity of the diagnostic
+    pub severity: Option<Severity>,
+    /// The message provided as an explanation for the diagnostic
+    pub message: String,
+    /// The source text causing the diagnostic
+    pub source: Option<String>,
+    /// Any related information to the diagnostic, if applicable
+    pub related_information: Option<Vec<RelatedInformation>>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.range, self.message)
+    }
+}
+
+/// Information relating to another diagnostic
+#[derive(Clone, Debug)]
+pub struct RelatedInformation {
+    /// The message provided for the related info
+    pub message: String,
+    /// The location of the related info
+    pub location: Location,
+}
+
+/// A location in a (optional) file
+#[derive(Clone, Debug)]
+pub struct Location {
+    /// An optional Url to the file
+    pub url: Option<url::Url>,
+    /// The range of the location
+    pub range: Range,
+}
@@ -4,17 +4,25 @@
 #[cfg(target_family = "wasm")]
 pub mod wasm_bindings;
 
+/// Builders for constructing the AST from code, instead of parsing text
+pub mod builder;
 /// Contains the types of the parser
 pub mod parser;
 /// Functions to perform transformations on the parsed AST
 pub mod transformer;
 
-/// Contains methods to lint the generated AST
+/// Contains methods to lint the generated AST. Needs the `lint` feature, which is enabled by default
+#[cfg(feature = "lint")]
 pub mod linter;
 
-use linter::Diagnostic;
+/// The diagnostic types are available without the `lint` feature, as they are part of the signature of [`parse_to_ast()`]
+mod diagnostic;
+pub use diagnostic::{Diagnostic, Location, RelatedInformation};
+
 use log::warn;
-use parser::{parse, ASTPrint, Document};
+pub use parser::BraceStyle;
+use parser::{parse, ASTPrint, DocItem, Document, NodeItem, PrintSettings};
+use std::sync::Arc;
 
 /// Defines which End of Line sequence to be used
 ///
@@ -72,12 +80,37 @@ impl std::fmt::Display for Indentation {
     }
 }
 
+impl Indentation {
+    /// Indents with `size` spaces if `use_spaces` is set, otherwise with tabs. `size` is ignored when using tabs
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::Indentation;
+    ///
+    /// assert_eq!(Indentation::new(true, 2).to_string(), "  ");
+    /// assert_eq!(Indentation::new(false, 2).to_string(), "\t");
+    /// ```
+    #[must_use]
+    pub const fn new(use_spaces: bool, size: usize) -> Self {
+        if use_spaces {
+            Self::Spaces(size)
+        } else {
+            Self::Tabs
+        }
+    }
+}
+
 impl From<Option<usize>> for Indentation {
     fn from(setting: Option<usize>) -> Self {
         setting.map_or(Self::Tabs, Self::Spaces)
     }
 }
 
+/// A function called with each value while formatting, returning the string to print instead, or `None` to keep the value. See [`Formatter::with_value_rewriter()`]
+///
+/// The function is shared, so a `Formatter` can be used from several threads
+pub type ValueRewriter = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
 /// Struct for holding the settings to use for formatting. use `self.format_text()` to format text
 ///
 /// Example:
@@ -98,11 +131,30 @@ pub struct Formatter {
     inline: Option<bool>,
     line_return: LineReturn,
     fail_silent: bool,
+    ensure_final_newline: Option<bool>,
+    brace_style: BraceStyle,
+    normalize_comments: bool,
+    sort_nodes: bool,
+    sort_mm_clauses: bool,
+    blank_line_between_top_level: bool,
+    preserve_alignment: bool,
+    keep_comment_indentation: bool,
+    space_in_empty_braces: bool,
+    max_line_length: usize,
+    value_rewriter: Option<ValueRewriter>,
+    normalize_indentation_only: bool,
 }
 
 impl Formatter {
     /// Constructs a new `Formatter` with the settings provided.
     ///
+    /// `inline` controls how nodes with a single item in the block are printed:
+    /// - `Some(true)`: Collapsed to one line, `node { key = val }`, if the line is short enough
+    /// - `Some(false)`: Always expanded to multiple lines, even if the node was on one line in the input
+    /// - `None`: Kept collapsed if it was collapsed in the input and is short enough, otherwise expanded
+    ///
+    /// Nodes with an empty block are always printed as `node {}`, regardless of `inline`, so `Some(false)` expands every block with content while keeping empty blocks on one line.
+    ///
     /// Example:
     /// ```
     /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
@@ -120,23 +172,258 @@ impl Formatter {
             inline,
             line_return,
             fail_silent: false,
+            ensure_final_newline: None,
+            brace_style: BraceStyle::OwnLine,
+            normalize_comments: false,
+            sort_nodes: false,
+            sort_mm_clauses: false,
+            blank_line_between_top_level: false,
+            preserve_alignment: false,
+            keep_comment_indentation: false,
+            space_in_empty_braces: false,
+            max_line_length: parser::DEFAULT_MAX_LINE_LENGTH,
+            value_rewriter: None,
+            normalize_indentation_only: false,
         }
     }
 
     /// Makes the parser fail silently, returning the original text instead of causing a Panic
     #[must_use]
-    pub const fn fail_silent(self) -> Self {
-        Self {
-            indentation: self.indentation,
-            inline: self.inline,
-            line_return: self.line_return,
-            fail_silent: true,
-        }
+    pub const fn fail_silent(mut self) -> Self {
+        self.fail_silent = true;
+        self
+    }
+
+    /// Sets how the end of the formatted text is handled.
+    ///
+    /// `Some(true)` makes the text end with exactly one line ending, `Some(false)` removes all trailing line endings,
+    /// and `None` keeps the line endings produced by the formatter. An empty document is always formatted to an empty string
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+    ///     .ensure_final_newline(Some(true));
+    /// assert_eq!(formatter.format_text("node {}").unwrap(), "node {}\n");
+    /// ```
+    #[must_use]
+    pub const fn ensure_final_newline(mut self, setting: Option<bool>) -> Self {
+        self.ensure_final_newline = setting;
+        self
+    }
+
+    /// Sets where the opening bracket of nodes spanning multiple lines is placed. See [`BraceStyle`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{BraceStyle, Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+    ///     .brace_style(BraceStyle::SameLine);
+    /// assert_eq!(formatter.format_text("node { key = val }").unwrap(), "node {\n\tkey = val\n}\n");
+    /// ```
+    #[must_use]
+    pub const fn brace_style(mut self, brace_style: BraceStyle) -> Self {
+        self.brace_style = brace_style;
+        self
+    }
+
+    /// Makes comments have exactly one space between `//` and the text, turning `//comment` into `// comment`, and removes trailing whitespace in comments.
+    ///
+    /// The spacing of comments starting with `///` or `//!`, like `//// divider`, is left unchanged
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+    ///     .normalize_comments(true);
+    /// assert_eq!(formatter.format_text("//comment").unwrap(), "// comment\n");
+    /// ```
+    #[must_use]
+    pub const fn normalize_comments(mut self, normalize_comments: bool) -> Self {
+        self.normalize_comments = normalize_comments;
+        self
+    }
+
+    /// Sorts child nodes alphabetically within each block, leaving assignments and patches in their original order. See [`transformer::sort_nodes()`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+    ///     .sort_nodes(true);
+    /// assert_eq!(
+    ///     formatter.format_text("node\n{\n\tb {}\n\ta {}\n}\n").unwrap(),
+    ///     "node\n{\n\ta {}\n\tb {}\n}\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn sort_nodes(mut self, sort_nodes: bool) -> Self {
+        self.sort_nodes = sort_nodes;
+        self
+    }
+
+    /// Sorts the predicates of `:HAS` blocks and the clauses of `:NEEDS` blocks alphabetically. The order rarely matters, but can, so this is off by default.
+    /// See [`transformer::sort_mm_clauses()`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+    ///     .sort_mm_clauses(true);
+    /// assert_eq!(
+    ///     formatter.format_text("@PART:HAS[#b[1],#a[2]]:NEEDS[B,A] {}\n").unwrap(),
+    ///     "@PART:HAS[#a[2],#b[1]]:NEEDS[A,B] {}\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn sort_mm_clauses(mut self, sort_mm_clauses: bool) -> Self {
+        self.sort_mm_clauses = sort_mm_clauses;
+        self
+    }
+
+    /// Makes sure there is an empty line between top level nodes. Existing empty lines are kept, and no empty line is added before the first node or after the last one.
+    ///
+    /// Comments directly above a node are kept together with the node, so the empty line is added above the comments
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+    ///     .blank_line_between_top_level(true);
+    /// assert_eq!(formatter.format_text("a {}\nb {}\n").unwrap(), "a {}\n\nb {}\n");
+    /// ```
+    #[must_use]
+    pub const fn blank_line_between_top_level(
+        mut self,
+        blank_line_between_top_level: bool,
+    ) -> Self {
+        self.blank_line_between_top_level = blank_line_between_top_level;
+        self
+    }
+
+    /// Keeps assignment operators aligned where they were already aligned in the input, instead of using a single space before them. See [`transformer::preserve_alignment()`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, None, LineReturn::LF)
+    ///     .preserve_alignment(true);
+    /// assert_eq!(
+    ///     formatter.format_text("node\n{\n\ta    = 1\n\tbcd  = 2\n}\n").unwrap(),
+    ///     "node\n{\n\ta    = 1\n\tbcd  = 2\n}\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn preserve_alignment(mut self, preserve_alignment: bool) -> Self {
+        self.preserve_alignment = preserve_alignment;
+        self
+    }
+
+    /// Keeps the whitespace before comments inside nodes as written, instead of indenting them to the depth of the block. Useful for comments used as section dividers.
+    /// Comments after other items on the same line are not affected
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, None, LineReturn::LF)
+    ///     .keep_comment_indentation(true);
+    /// assert_eq!(
+    ///     formatter.format_text("node\n{\n// Section\n  key = val\n}\n").unwrap(),
+    ///     "node\n{\n// Section\n\tkey = val\n}\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn keep_comment_indentation(mut self, keep_comment_indentation: bool) -> Self {
+        self.keep_comment_indentation = keep_comment_indentation;
+        self
+    }
+
+    /// Prints empty blocks as `node { }`, with a space between the brackets, instead of `node {}`
+    #[must_use]
+    pub const fn space_in_empty_braces(mut self, space_in_empty_braces: bool) -> Self {
+        self.space_in_empty_braces = space_in_empty_braces;
+        self
+    }
+
+    /// Sets the length of the longest line that a node can be collapsed to, 72 by default
+    ///
+    /// Node headers longer than this, like `@PART[*]:HAS[...]:NEEDS[...]`, are never wrapped, since KSP, Module Manager and the parser require a header on one line
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    /// assert_eq!(formatter.format_text("node { key = val }").unwrap(), "node { key = val }\n");
+    /// assert_eq!(
+    ///     formatter.max_line_length(10).format_text("node { key = val }").unwrap(),
+    ///     "node\n{\n\tkey = val\n}\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Calls `rewriter` with every value before printing, replacing the value with the returned string. Values where `rewriter` returns `None` are left unchanged.
+    /// Keys and comments are never passed to `rewriter`. Returned strings containing `}`, `//` or a line break would end the value early, so they are ignored. See [`transformer::rewrite_values`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+    ///     .with_value_rewriter(|val| val.parse::<f64>().ok().map(|n| format!("{n:.1}")));
+    /// assert_eq!(
+    ///     formatter.format_text("node { mass = 0.125 }\n").unwrap(),
+    ///     "node { mass = 0.1 }\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn with_value_rewriter(
+        mut self,
+        rewriter: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.value_rewriter = Some(Arc::new(rewriter));
+        self
+    }
+
+    /// Only fixes the indentation of each line, leaving the rest of the text as written. This is the least invasive way of formatting,
+    /// for example in large shared repositories.
+    ///
+    /// Nodes are not collapsed or expanded, and blank lines, comments, spacing and line endings are kept.
+    /// All other settings except the indentation are ignored. The text is still parsed, so text with errors is not changed
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+    ///     .normalize_indentation_only(true);
+    /// assert_eq!(
+    ///     formatter.format_text("node\n{\n  key  =  val\n    child { a = b }\n}\n").unwrap(),
+    ///     "node\n{\n\tkey  =  val\n\tchild { a = b }\n}\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn normalize_indentation_only(mut self, normalize_indentation_only: bool) -> Self {
+        self.normalize_indentation_only = normalize_indentation_only;
+        self
     }
 
     /// Takes the provided text and formats it according to the settings of the `Formatter`
     ///
-    /// If the formatter is set to fail silently, and formatting fails, the orginal text is returned unchanged
+    /// If the formatter is set to fail silently, and formatting fails, the orginal text is returned unchanged.
+    /// Empty text, or text with only whitespace, is formatted to an empty string, without a line ending
     ///
     /// Example:
     /// ```
@@ -163,14 +450,220 @@ impl Formatter {
             }
         }
     }
+
+    /// Formats only the top level items overlapping the byte range `byte_start..byte_end` of `text`, like a node the user just edited.
+    /// Returns the range of `text` to replace, and the formatted text to replace it with. The range starts at the first overlapping item, and ends at the end of the last one.
+    /// If no item overlaps the byte range, an empty range at `byte_start` and an empty string are returned.
+    ///
+    /// The whole text is parsed, and the same settings as [`Formatter::format_text()`] are used, except the ones that apply to the whole document:
+    /// the final newline, and empty lines between top level nodes.
+    /// When only normalizing indentation, the range covers the whole lines of the items, and the text is taken from those lines after reindenting the document.
+    /// Errors are returned regardless of the formatter failing silently
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{parser::Range, Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    /// let text = "a {  x = 1 }\nb {  y = 2 }\n";
+    /// let start = text.find('y').unwrap();
+    /// assert_eq!(
+    ///     formatter.format_range(text, start, start + 1).unwrap(),
+    ///     (Range::new(2, 1, 2, 13), "b { y = 2 }".to_owned())
+    /// );
+    /// ```
+    /// # Errors
+    /// Returns the errors found while parsing the text
+    pub fn format_range(
+        &self,
+        text: &str,
+        byte_start: usize,
+        byte_end: usize,
+    ) -> Result<(parser::Range, String), Vec<parser::Error>> {
+        let (parsed_document, errors) = parse(text);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let span = parser::Range::from_offsets(text, byte_start, byte_end);
+        let parsed_document = if self.normalize_indentation_only {
+            parsed_document
+        } else {
+            transform_document(parsed_document, self)
+        };
+        let overlapping = |item: &DocItem| {
+            let range = item.range();
+            !matches!(item, DocItem::EmptyLine(_))
+                && range.start <= span.end
+                && span.start <= range.end
+        };
+        let statements = &parsed_document.statements;
+        let (Some(first), Some(last)) = (
+            statements.iter().position(overlapping),
+            statements.iter().rposition(overlapping),
+        ) else {
+            return Ok((span.to_start(), String::new()));
+        };
+        let indentation = self.indentation.to_string();
+        if self.normalize_indentation_only {
+            return Ok(reindent_lines(
+                text,
+                &parsed_document,
+                &indentation,
+                statements[first].range().start.line,
+                statements[last].range().end.line,
+            ));
+        }
+        let line_ending = self.line_ending(text);
+        let settings = self.print_settings();
+        let output: String = statements[first..=last]
+            .iter()
+            .map(|item| item.ast_print(0, &indentation, line_ending, &settings))
+            .collect();
+        // The range ends at the end of the last item, before its line ending
+        let output = output
+            .strip_suffix(line_ending)
+            .unwrap_or(&output)
+            .to_owned();
+        let range = parser::Range {
+            start: statements[first].range().start,
+            end: statements[last].range().end,
+        };
+        Ok((range, output))
+    }
+
+    /// Checks if the text can be formatted, without formatting it. Use this instead of [`Formatter::format_text()`] when the output is not needed
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    /// assert!(formatter.check("node { key = val }").is_ok());
+    /// ```
+    /// # Errors
+    /// Returns the errors found while parsing the text, regardless of the formatter failing silently
+    pub fn check(&self, text: &str) -> Result<(), Vec<parser::Error>> {
+        let errors = validate(text);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Formats text that might not be valid UTF-8. Invalid sequences are replaced with `�` before formatting, like the `--lossy` flag of the CLI.
+    ///
+    /// Each replaced sequence can be several bytes long, so positions in errors refer to the text after replacement, not to `bytes`
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    /// assert_eq!(formatter.format_bytes(b"node {   key = val }").unwrap(), "node { key = val }\n");
+    /// ```
+    /// # Errors
+    /// Same as [`Formatter::format_text()`]
+    pub fn format_bytes(&self, bytes: &[u8]) -> Result<String, Vec<parser::Error>> {
+        self.format_text(&String::from_utf8_lossy(bytes))
+    }
+
+    /// Formats several texts with the same settings, returning the results in the same order as the inputs.
+    ///
+    /// The texts are formatted one at a time, in order, each with [`Formatter::format_text()`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    /// let results = formatter.format_many(["a {}", "b {}"]);
+    /// assert_eq!(results, vec![Ok("a {}\n".to_owned()), Ok("b {}\n".to_owned())]);
+    /// ```
+    pub fn format_many<'a>(
+        &self,
+        inputs: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<Result<String, Vec<parser::Error>>> {
+        inputs
+            .into_iter()
+            .map(|text| self.format_text(text))
+            .collect()
+    }
+
+    /// Formats the parts of the text that could be parsed, leaving the parts with errors unchanged.
+    ///
+    /// Unlike [`Formatter::format_text()`], a single broken node does not prevent the rest of the text from being formatted.
+    /// The errors found while parsing are returned alongside the output
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    /// let (output, errors) = formatter.format_best_effort("node { key = val }");
+    /// assert_eq!(output, "node\n{\n\tkey = val\n}\n");
+    /// assert!(errors.is_empty());
+    /// ```
+    #[must_use]
+    pub fn format_best_effort(&self, text: &str) -> (String, Vec<parser::Error>) {
+        let (parsed_document, errors) = parse(text);
+        let parsed_document = transform_document(parsed_document, self);
+        (print_document(&parsed_document, text, self), errors)
+    }
+
+    /// Formats only the node at `path`, like `PART[foo]/MODULE[bar]`. Returns `None` if no node matches the path.
+    ///
+    /// The node is printed without indentation, as if it was at the top level. See [`Document::node_at_path()`] for how the path is matched
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::{Formatter, Indentation, LineReturn};
+    ///
+    /// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    /// let text = "PART[foo] { MODULE[bar] {   key   =   val } }";
+    /// let output = formatter.format_node_at_path(text, "PART[foo]/MODULE[bar]");
+    /// assert_eq!(output.as_deref(), Some("MODULE[bar]\n{\n\tkey = val\n}\n"));
+    /// ```
+    #[must_use]
+    pub fn format_node_at_path(&self, text: &str, path: &str) -> Option<String> {
+        let (parsed_document, _errors) = parse(text);
+        let parsed_document = transform_document(parsed_document, self);
+        let node = parsed_document.node_at_path(path)?;
+        Some(node.ast_print(
+            0,
+            &self.indentation.to_string(),
+            self.line_ending(text),
+            &self.print_settings(),
+        ))
+    }
+
+    /// The line ending to use when formatting `text`
+    fn line_ending(&self, text: &str) -> &'static str {
+        let use_crlf = if matches!(self.line_return, LineReturn::Identify) {
+            text.contains("\r\n")
+        } else {
+            matches!(self.line_return, LineReturn::CRLF)
+        };
+        if use_crlf {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    /// The settings used when printing the AST
+    const fn print_settings(&self) -> PrintSettings {
+        PrintSettings::new(self.inline)
+            .brace_style(self.brace_style)
+            .normalize_comments(self.normalize_comments)
+            .blank_line_between_top_level(self.blank_line_between_top_level)
+            .keep_comment_indentation(self.keep_comment_indentation)
+            .space_in_empty_braces(self.space_in_empty_braces)
+            .max_line_length(self.max_line_length)
+    }
 }
 
 fn ast_format(text: &str, settings: &Formatter) -> Result<String, Vec<parser::Error>> {
-    let use_crlf = if matches!(settings.line_return, LineReturn::Identify) {
-        text.contains("\r\n")
-    } else {
-        matches!(settings.line_return, LineReturn::CRLF)
-    };
     let (parsed_document, errors) = parse(text);
     for error in &errors {
         warn!("{error:#?}");
@@ -178,25 +671,271 @@ fn ast_format(text: &str, settings: &Formatter) -> Result<String, Vec<parser::Er
     if !errors.is_empty() {
         return Err(errors);
     }
+    if settings.normalize_indentation_only {
+        return Ok(reindent(
+            text,
+            &parsed_document,
+            &settings.indentation.to_string(),
+        ));
+    }
     // let parsed_document = transformer::assignments_first(parsed_document)?;
     // let parsed_document = transformer::assignment_padding(parsed_document);
-    let line_ending = if use_crlf { "\r\n" } else { "\n" };
-    Ok(parsed_document.ast_print(
+    let parsed_document = transform_document(parsed_document, settings);
+    Ok(print_document(&parsed_document, text, settings))
+}
+
+/// Applies the transformers enabled in the formatter to the document
+fn transform_document<'a>(mut parsed_document: Document<'a>, settings: &Formatter) -> Document<'a> {
+    // Nodes are sorted by their header, so the clauses in it are sorted first
+    if settings.sort_mm_clauses {
+        parsed_document = transformer::sort_mm_clauses(parsed_document);
+    }
+    if settings.sort_nodes {
+        parsed_document = transformer::sort_nodes(parsed_document);
+    }
+    if settings.preserve_alignment {
+        parsed_document = transformer::preserve_alignment(parsed_document);
+    }
+    if let Some(rewriter) = &settings.value_rewriter {
+        parsed_document = transformer::rewrite_values(parsed_document, rewriter.as_ref());
+    }
+    parsed_document
+}
+
+/// Prints the document using the settings of the formatter. `text` is the source of the document, used to identify line endings
+fn print_document(parsed_document: &Document, text: &str, settings: &Formatter) -> String {
+    let line_ending = settings.line_ending(text);
+    let output = parsed_document.ast_print(
         0,
         &settings.indentation.to_string(),
         line_ending,
-        settings.inline,
-    ))
+        &settings.print_settings(),
+    );
+    match settings.ensure_final_newline {
+        Some(ensure) => {
+            let mut output = output.trim_end_matches(['\r', '\n']).to_owned();
+            if ensure && !output.is_empty() {
+                output.push_str(line_ending);
+            }
+            output
+        }
+        None => output,
+    }
+}
+
+/// Replaces the indentation of each line with `indentation`, repeated by the depth of the line, and keeps the rest of the text.
+/// The depth is taken from the ranges of the nodes in `doc`, which is parsed from `text`, so brackets in values and comments don't change it
+fn reindent(text: &str, doc: &Document, indentation: &str) -> String {
+    let line_count = text.split_inclusive('\n').count();
+    let mut depths = LineDepths {
+        item_start: vec![None; line_count + 1],
+        inside_node: vec![0; line_count + 1],
+    };
+    for item in &doc.statements {
+        match item {
+            DocItem::Node(node) => depths.add_node(node, 0),
+            item => depths.add_item(item.range(), 0),
+        }
+    }
+    let mut output = String::with_capacity(text.len());
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let content = line.trim_start_matches([' ', '\t']);
+        // Empty lines are kept empty, without any indentation
+        if !content.trim_end_matches(['\r', '\n']).is_empty() {
+            output.push_str(&indentation.repeat(depths.depth(index + 1)));
+        }
+        output.push_str(content);
+    }
+    output
+}
+
+/// Reindents the document, like [`reindent()`], and returns the range of the lines `first_line..=last_line` without the last line ending, and their reindented text
+fn reindent_lines(
+    text: &str,
+    doc: &Document,
+    indentation: &str,
+    first_line: u32,
+    last_line: u32,
+) -> (parser::Range, String) {
+    let skip = first_line as usize - 1;
+    let count = (last_line - first_line) as usize + 1;
+    let lines = |text: &str| -> String {
+        let lines: String = text.split_inclusive('\n').skip(skip).take(count).collect();
+        lines
+            .strip_suffix('\n')
+            .map_or(lines.as_str(), |lines| {
+                lines.strip_suffix('\r').unwrap_or(lines)
+            })
+            .to_owned()
+    };
+    let source = lines(text);
+    let last_line_len = source
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count();
+    let range = parser::Range::new(first_line, 1, last_line, last_line_len as u32 + 1);
+    (range, lines(&reindent(text, doc, indentation)))
+}
+
+/// The depth of each line of a document, indexed by the line number
+struct LineDepths {
+    /// The depth of the outermost item starting on the line
+    item_start: Vec<Option<usize>>,
+    /// The depth of the innermost node spanning the line, used for lines with only brackets
+    inside_node: Vec<usize>,
+}
+
+impl LineDepths {
+    fn add_item(&mut self, range: parser::Range, depth: usize) {
+        let start = &mut self.item_start[range.start.line as usize];
+        *start = Some(start.map_or(depth, |other| other.min(depth)));
+    }
+
+    fn add_node(&mut self, node: &parser::Ranged<parser::Node>, depth: usize) {
+        let range = node.get_range();
+        self.add_item(range, depth);
+        for line in range.start.line..=range.end.line {
+            let inside = &mut self.inside_node[line as usize];
+            *inside = (*inside).max(depth);
+        }
+        for item in &node.block {
+            match item {
+                NodeItem::Node(child) => self.add_node(child, depth + 1),
+                item => self.add_item(item.range(), depth + 1),
+            }
+        }
+    }
+
+    fn depth(&self, line: usize) -> usize {
+        self.item_start[line].unwrap_or(self.inside_node[line])
+    }
 }
 
 /// Parses the text to a `Document` struct
 /// # Errors
-/// If any part of the parser fails, the returned error indicates what caused it, where it occured, and the source text for the error
-pub fn parse_to_ast(text: &str) -> Result<Document, (Vec<parser::Error>, Vec<Diagnostic>)> {
-    let (parsed_document, errors) = parse(text);
-    let diagnostics = linter::lint_ast(&parsed_document, None);
+/// If any part of the parser fails, the returned error indicates what caused it, where it occured, and the source text for the error.
+/// Without the `lint` feature, the diagnostics are always empty
+pub fn parse_to_ast(text: &str) -> Result<Document<'_>, (Vec<parser::Error>, Vec<Diagnostic>)> {
+    let (parsed_document, errors, diagnostics) = parse_partial(text);
     if !errors.is_empty() || !diagnostics.is_empty() {
         return Err((errors, diagnostics));
     }
     Ok(parsed_document)
 }
+
+/// Parses and lints the text, always returning the `Document`, even if it contains errors.
+///
+/// Parts of the text that failed to parse are kept as error items in the `Document`, so the rest of the tree can still be used
+#[must_use]
+pub fn parse_partial(text: &str) -> (Document<'_>, Vec<parser::Error>, Vec<Diagnostic>) {
+    let (parsed_document, errors) = parse(text);
+    #[cfg(feature = "lint")]
+    let diagnostics = linter::lint_ast(&parsed_document, text, None);
+    // Without the linter, only the syntax errors are reported
+    #[cfg(not(feature = "lint"))]
+    let diagnostics = vec![];
+    (parsed_document, errors, diagnostics)
+}
+
+/// Only checks the text for syntax errors, without linting it or returning the parsed `Document`
+///
+/// Example:
+/// ```
+/// assert!(ksp_cfg_formatter::validate("node { key = val }").is_empty());
+/// assert_eq!(ksp_cfg_formatter::validate("node {").len(), 1);
+/// ```
+#[must_use]
+pub fn validate(text: &str) -> Vec<parser::Error> {
+    parse(text).1
+}
+
+/// Checks that every `{` is closed by a `}`, without parsing the text. Brackets inside `//` comments and values, like `key = a{b`, are ignored.
+///
+/// Each unmatched bracket is reported on its own, with the range of just that bracket. Run this before [`validate`] or [`parse_to_ast`]
+/// to get precise positions, as a missing `}` can make the parser report errors far from the actual problem
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{parser::Range, unbalanced_brackets};
+///
+/// assert!(unbalanced_brackets("node { key = val } // }").is_empty());
+/// let errors = unbalanced_brackets("node {\n\tchild {\n}\n");
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].range, Range::new(1, 6, 1, 7));
+/// ```
+#[must_use]
+pub fn unbalanced_brackets(text: &str) -> Vec<parser::Error> {
+    let bracket_error = |offset: usize, code, message: &str| parser::Error {
+        severity: parser::Severity::Error,
+        code,
+        range: parser::Range::from_offsets(text, offset, offset + 1),
+        source: text[offset..=offset].to_owned(),
+        message: message.to_owned(),
+        context: None,
+    };
+    let mut errors = vec![];
+    let mut open = vec![];
+    let mut in_comment = false;
+    // A value starts after the `=` of an assignment, and ends like in the parser, at a comment, a `}` or the end of the line
+    let mut in_value = false;
+    // Depth of `[` on the line, as a `=` inside `[]`, like in a `:HAS` block, does not start a value
+    let mut square_depth = 0_usize;
+    let mut chars = text.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\n' => {
+                in_comment = false;
+                in_value = false;
+                square_depth = 0;
+            }
+            _ if in_comment => (),
+            '/' if chars.peek().is_some_and(|(_, next)| *next == '/') => in_comment = true,
+            '{' if in_value => (),
+            '{' => open.push(offset),
+            '}' => {
+                in_value = false;
+                let closed = open.pop();
+                if closed.is_none() {
+                    errors.push(bracket_error(
+                        offset,
+                        parser::codes::UNEXPECTED_TEXT,
+                        "`}` does not close any `{`",
+                    ));
+                }
+            }
+            _ if in_value => (),
+            '[' => square_depth += 1,
+            ']' => square_depth = square_depth.saturating_sub(1),
+            '=' if square_depth == 0 => in_value = true,
+            _ => (),
+        }
+    }
+    errors.extend(open.into_iter().map(|offset| {
+        bracket_error(
+            offset,
+            parser::codes::UNCLOSED_BRACKET,
+            "`{` is never closed by a `}`",
+        )
+    }));
+    errors.sort_by_key(|error| error.range);
+    errors
+}
+
+/// Formatting only depends on the parser, and works without the lint rules
+#[cfg(all(test, not(feature = "lint")))]
+mod no_lint_tests {
+    #[test]
+    fn format_without_lint() {
+        let formatter =
+            crate::Formatter::new(crate::Indentation::Tabs, None, crate::LineReturn::LF);
+        assert_eq!(
+            formatter.format_text("node { @key = val }").unwrap(),
+            "node { @key = val }\n"
+        );
+        // The operator inside a node without one is not linted
+        assert!(crate::parse_to_ast("node\n{\n\t@key = val\n}\n").is_ok());
+        assert!(crate::parse_to_ast("node {").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::parser::{DocItem, Document};
+use crate::parser::{DocItem, Document, Range, Severity};
 
 use super::{Diagnostic, Lintable, LinterState, LinterStateResult};
 
@@ -12,7 +12,7 @@ impl<'a> Lintable for Document<'a> {
             let (mut diagnostics, res) = statement.lint(state);
             items.append(&mut diagnostics);
             // Merge result into this result
-            result.top_level_no_op_result |= res.map_or(false, |res| res.top_level_no_op_result);
+            result.top_level_no_op_result |= res.is_some_and(|res| res.top_level_no_op_result);
         }
         (items, Some(result))
     }
@@ -23,8 +23,120 @@ impl<'a> Lintable for DocItem<'a> {
         match self {
             DocItem::Node(n) => n.lint(state),
             DocItem::Comment(c) => c.lint(state),
-            DocItem::EmptyLine => (vec![], None),
+            DocItem::EmptyLine(_) => (vec![], None),
             DocItem::Error(_e) => (vec![], None),
         }
     }
 }
+
+/// Checks for lines where the indentation contains both tabs and spaces.
+/// Only one diagnostic is emitted for the whole document, pointing at the first line
+pub(super) fn mixed_indentation(source: &str) -> Option<Diagnostic> {
+    let mut mixed_lines = source.lines().enumerate().filter_map(|(i, line)| {
+        let indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        (indentation.contains(' ') && indentation.contains('\t')).then(|| {
+            let line_number = i as u32 + 1;
+            Range::new(
+                line_number,
+                1,
+                line_number,
+                indentation.chars().count() as u32 + 1,
+            )
+        })
+    });
+    let first = mixed_lines.next()?;
+    let count = mixed_lines.count() + 1;
+    Some(Diagnostic {
+        range: first,
+        severity: Some(Severity::Info),
+        message: format!(
+            "Indentation mixes tabs and spaces on {count} line(s), starting on line {}. The formatter will replace it",
+            first.start.line
+        ),
+        source: Some(super::rules::MIXED_INDENTATION.to_owned()),
+        ..Default::default()
+    })
+}
+
+/// Checks for a source using both `\r\n` and lone `\n` line endings, which is often left behind by a merge.
+/// Only one diagnostic is emitted for the whole document, pointing at the end of the first line that differs from the first line ending
+pub(super) fn mixed_line_endings(source: &str) -> Option<Diagnostic> {
+    // The text of each line before the `\n`, and if the line ends with `\r\n`
+    let mut lines = source
+        .split_inclusive('\n')
+        .filter_map(|line| line.strip_suffix('\n'))
+        .map(|line| match line.strip_suffix('\r') {
+            Some(line) => (line, true),
+            None => (line, false),
+        });
+    let (_, first_crlf) = lines.next()?;
+    let (i, (line, _)) = lines
+        .enumerate()
+        .find(|(_, (_, crlf))| *crlf != first_crlf)?;
+    let crlf_count = source.matches("\r\n").count();
+    let lf_count = source.matches('\n').count() - crlf_count;
+    let line_number = i as u32 + 2;
+    let column = line.chars().count() as u32 + 1;
+    Some(Diagnostic {
+        range: Range::new(line_number, column, line_number, column),
+        severity: Some(Severity::Info),
+        message: format!(
+            "Line endings mix CRLF ({crlf_count} line(s)) and LF ({lf_count} line(s)), starting on line {line_number}. This is often left behind by a merge"
+        ),
+        source: Some(super::rules::MIXED_LINE_ENDINGS.to_owned()),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_indentation() {
+        let source = "node\n{\n\t key = val\n\tkey = val\n  \tkey = val\n}\n";
+        let diagnostic = mixed_indentation(source).expect("Expected a diagnostic");
+        assert_eq!(diagnostic.range, Range::new(3, 1, 3, 3));
+        assert_eq!(diagnostic.severity, Some(Severity::Info));
+        assert!(diagnostic.message.contains("2 line(s)"));
+    }
+
+    #[test]
+    fn test_consistent_indentation() {
+        let source = "node\n{\n\tkey = val\n\tchild\n\t{\n\t\tkey = a b\t c\n\t}\n}\n";
+        assert!(mixed_indentation(source).is_none());
+    }
+
+    #[test]
+    fn test_mixed_line_endings() {
+        let source = "node\r\n{\r\n\tkey = val\n}\r\n";
+        let diagnostic = mixed_line_endings(source).expect("Expected a diagnostic");
+        assert_eq!(diagnostic.range, Range::new(3, 11, 3, 11));
+        assert_eq!(diagnostic.severity, Some(Severity::Info));
+        assert!(diagnostic
+            .message
+            .contains("CRLF (3 line(s)) and LF (1 line(s))"));
+
+        assert!(mixed_line_endings("node\r\n{\r\n}\r\n").is_none());
+        assert!(mixed_line_endings("node\n{\n}").is_none());
+    }
+
+    #[test]
+    fn test_mixed_line_endings_is_opt_in() {
+        use crate::linter::{lint_ast_with_config, rules, LintConfig};
+        let source = "node\r\n{\n}\r\n";
+        let (ast, errors) = crate::parser::parse(source);
+        assert!(errors.is_empty());
+        let count = |config: &LintConfig| {
+            lint_ast_with_config(&ast, source, None, config)
+                .iter()
+                .filter(|d| d.source.as_deref() == Some(rules::MIXED_LINE_ENDINGS))
+                .count()
+        };
+        assert_eq!(count(&LintConfig::default()), 0);
+        assert_eq!(
+            count(&LintConfig::default().opt_in([rules::MIXED_LINE_ENDINGS])),
+            1
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use itertools::Itertools;
 
-use crate::parser::Ranged;
+use crate::parser::{MatchType, Ranged};
 
 use super::{Diagnostic, Lintable};
 
@@ -42,14 +42,31 @@ impl<'a> Lintable for Ranged<crate::parser::HasPredicate<'a>> {
                 negated: _,
                 key: _,
                 value,
-                match_type: _,
+                match_type,
             } => {
                 if let Some(value) = value {
-                    if value.is_empty() {
+                    if value.is_empty() && state.enabled(super::rules::EMPTY_HAS_VALUE) {
                         items.push(Diagnostic {
                             range: value.get_range(),
                             severity: Some(crate::parser::Severity::Info),
                             message: "Expected value".to_owned(),
+                            source: Some(super::rules::EMPTY_HAS_VALUE.to_owned()),
+                            ..Default::default()
+                        });
+                    }
+                    if !matches!(match_type, MatchType::Literal)
+                        && !value.is_empty()
+                        && value.trim().parse::<f64>().is_err()
+                        && state.enabled(super::rules::NON_NUMERIC_COMPARISON)
+                    {
+                        items.push(Diagnostic {
+                            range: value.get_range(),
+                            severity: Some(crate::parser::Severity::Warning),
+                            message: format!(
+                                "`{match_type}` compares numerically, but `{}` is not a number",
+                                value.as_ref()
+                            ),
+                            source: Some(super::rules::NON_NUMERIC_COMPARISON.to_owned()),
                             ..Default::default()
                         });
                     }
@@ -59,3 +76,27 @@ impl<'a> Lintable for Ranged<crate::parser::HasPredicate<'a>> {
         (items, None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        linter::{diagnostics_for, rules},
+        parser::Range,
+    };
+
+    #[test]
+    fn test_non_numeric_comparison() {
+        let rule = rules::NON_NUMERIC_COMPARISON;
+        assert!(diagnostics_for("@PART:HAS[#mass[>2.0]] { }\n", rule).is_empty());
+        assert!(diagnostics_for("@PART:HAS[#mass[<-1]] { }\n", rule).is_empty());
+        assert!(diagnostics_for("@PART:HAS[#title[abc]] { }\n", rule).is_empty());
+
+        let diagnostics = diagnostics_for("@PART:HAS[#mass[>abc]] { }\n", rule);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].severity,
+            Some(crate::parser::Severity::Warning)
+        );
+        assert_eq!(diagnostics[0].range, Range::new(1, 18, 1, 21));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::parser::{Index, Ranged};
+
+use super::{rules, Diagnostic, Lintable, LinterState, LinterStateResult};
+
+impl Lintable for Ranged<Index> {
+    fn lint(&self, state: &LinterState) -> (Vec<Diagnostic>, Option<LinterStateResult>) {
+        let Index::Invalid(raw) = self.as_ref() else {
+            return (vec![], None);
+        };
+        if !state.enabled(rules::INVALID_INDEX) {
+            return (vec![], None);
+        }
+        let message = if raw.is_empty() {
+            "Expected an index after `,`. Use `*` for all matches, or an integer".to_owned()
+        } else {
+            format!("Invalid index `{raw}`. Use `*` for all matches, or an integer")
+        };
+        (
+            vec![Diagnostic {
+                range: self.get_range(),
+                severity: Some(crate::parser::Severity::Error),
+                message,
+                source: Some(rules::INVALID_INDEX.to_owned()),
+                ..Default::default()
+            }],
+            None,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::linter::{diagnostics_for, rules};
+
+    #[test]
+    fn test_valid_indices() {
+        for source in [
+            "@NODE,* {}\n",
+            "@NODE,0 {}\n",
+            "@NODE,-1:HAS[#key] {}\n",
+            "@NODE { @key,2 = val }\n",
+            "@NODE { @key,*[1, ] = val }\n",
+        ] {
+            assert!(
+                diagnostics_for(source, rules::INVALID_INDEX).is_empty(),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_indices() {
+        for (source, raw) in [
+            ("@NODE, {}\n", ""),
+            ("@NODE,abc:HAS[#key] {}\n", "abc"),
+            ("@NODE,1a {}\n", "1a"),
+            ("@NODE { @key,x = val }\n", "x"),
+            ("@NODE { @key,99999999999 = val }\n", "99999999999"),
+        ] {
+            let diagnostics = diagnostics_for(source, rules::INVALID_INDEX);
+            assert_eq!(diagnostics.len(), 1, "{source}");
+            assert!(diagnostics[0].message.contains(&format!("`{raw}`")) || raw.is_empty());
+        }
+    }
+}
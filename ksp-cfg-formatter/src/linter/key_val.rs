@@ -1,6 +1,8 @@
 use crate::parser::{AssignmentOperator, KeyVal, Operator, Range, Ranged};
 
-use super::{Diagnostic, Lintable, LinterState, LinterStateResult, Location, RelatedInformation};
+use super::{
+    rules, Diagnostic, Lintable, LinterState, LinterStateResult, Location, RelatedInformation,
+};
 
 impl<'a> Lintable for Ranged<KeyVal<'a>> {
     fn lint(&self, state: &LinterState) -> (Vec<Diagnostic>, Option<LinterStateResult>) {
@@ -15,6 +17,16 @@ impl<'a> Lintable for Ranged<KeyVal<'a>> {
         items.append(&mut noop_but_mm(self, state));
         // Regex was used without the operator being Edit
         items.append(&mut check_regex_not_edit(self, state));
+        if let Some(diag) = tab_in_value(self, state) {
+            items.push(diag);
+        }
+        if let Some(diag) = brace_in_value(self, state) {
+            items.push(diag);
+        }
+        items.append(&mut malformed_value_references(self, state));
+        if let Some(index) = &self.index {
+            items.append(&mut index.lint(state).0);
+        }
 
         (items, Some(result))
     }
@@ -25,6 +37,9 @@ fn op_in_noop_node(
     state: &LinterState,
     result: &mut LinterStateResult,
 ) -> Option<Diagnostic> {
+    if !state.enabled(rules::UNEXPECTED_OPERATOR) {
+        return None;
+    }
     if let Some(top_level_no_op) = &state.top_level_no_op {
         if let Some(operator) = &key_val.operator {
             result.top_level_no_op_result = true;
@@ -32,8 +47,11 @@ fn op_in_noop_node(
                 range: operator.get_range(),
 
                 severity: Some(crate::parser::Severity::Warning),
-                source: Some("Unexpected_operator".to_owned()),
-                message: "Key has operator, even though the top level does not!".to_owned(),
+                source: Some(rules::UNEXPECTED_OPERATOR.to_owned()),
+                message: format!(
+                    "Key has operator `{}`, even though the top level does not!",
+                    operator.as_ref()
+                ),
                 related_information: Some(vec![super::RelatedInformation {
                     location: top_level_no_op.clone(),
                     message: "This is where it happened".to_owned(),
@@ -48,6 +66,9 @@ fn op_in_noop_node(
 }
 
 fn check_regex_not_edit(key_val: &Ranged<KeyVal>, state: &LinterState) -> Vec<Diagnostic> {
+    if !state.enabled(rules::REGEX_WITHOUT_EDIT) {
+        return vec![];
+    }
     if matches!(
         key_val.assignment_operator.as_ref(),
         AssignmentOperator::RegexReplace
@@ -69,12 +90,79 @@ fn check_regex_not_edit(key_val: &Ranged<KeyVal>, state: &LinterState) -> Vec<Di
                 message: "Expected Edit operator here".to_owned()
             }]),
             severity: Some(crate::parser::Severity::Warning),
-            ..Default::default()
+            source: Some(rules::REGEX_WITHOUT_EDIT.to_owned()),
         }];
     }
     vec![]
 }
 
+fn tab_in_value(key_val: &KeyVal, state: &LinterState) -> Option<Diagnostic> {
+    if !state.enabled(rules::TAB_IN_VALUE) || !key_val.val.contains('\t') {
+        return None;
+    }
+    Some(Diagnostic {
+        range: key_val.val.get_range(),
+        severity: Some(crate::parser::Severity::Info),
+        message: "The value contains a tab character, which is likely a mistake".to_owned(),
+        source: Some(rules::TAB_IN_VALUE.to_owned()),
+        ..Default::default()
+    })
+}
+
+fn brace_in_value(key_val: &KeyVal, state: &LinterState) -> Option<Diagnostic> {
+    if !state.enabled(rules::BRACE_IN_VALUE) || !key_val.val.contains('{') {
+        return None;
+    }
+    Some(Diagnostic {
+        range: key_val.val.get_range(),
+        severity: Some(crate::parser::Severity::Warning),
+        message: "The value contains `{`, which is likely a block that is missing a line ending before it".to_owned(),
+        source: Some(rules::BRACE_IN_VALUE.to_owned()),
+        ..Default::default()
+    })
+}
+
+/// Finds value references, like `maxThrust = #$/someKey$`, that are not closed by a `$` or have no path.
+/// The paths are not resolved, so this is only a check of the syntax
+fn malformed_value_references(key_val: &Ranged<KeyVal>, state: &LinterState) -> Vec<Diagnostic> {
+    if !state.enabled(rules::MALFORMED_VALUE_REFERENCE) {
+        return vec![];
+    }
+    let value: &str = &key_val.val;
+    let start = key_val.val.get_range().start;
+    // Values are on a single line, so positions in the value are found from the column
+    let col = |offset: usize| start.col + value[..offset].chars().count() as u32;
+    let mut diagnostics = vec![];
+    let mut rest = 0;
+    while let Some(found) = value[rest..].find("#$") {
+        let reference_start = rest + found;
+        let path_start = reference_start + 2;
+        let (end, message) = match value[path_start..].find('$') {
+            Some(0) => (
+                path_start + 1,
+                "The value reference is missing the path between `#$` and `$`",
+            ),
+            Some(len) => {
+                rest = path_start + len + 1;
+                continue;
+            }
+            None => (
+                value.len(),
+                "The value reference is missing a closing `$`, like `#$/path/key$`",
+            ),
+        };
+        diagnostics.push(Diagnostic {
+            range: Range::new(start.line, col(reference_start), start.line, col(end)),
+            severity: Some(crate::parser::Severity::Info),
+            message: message.to_owned(),
+            source: Some(rules::MALFORMED_VALUE_REFERENCE.to_owned()),
+            ..Default::default()
+        });
+        rest = end;
+    }
+    diagnostics
+}
+
 // :NEEDS is allowed
 fn range_for_rest_of_name(key_val: &KeyVal) -> Vec<crate::parser::Range> {
     let mut ranges = vec![];
@@ -95,7 +183,10 @@ fn range_for_rest_of_name(key_val: &KeyVal) -> Vec<crate::parser::Range> {
 }
 
 fn noop_but_mm(key_val: &Ranged<KeyVal>, state: &LinterState) -> Vec<Diagnostic> {
-    if key_val.operator.is_some() || key_val.path.is_some() {
+    if key_val.operator.is_some()
+        || key_val.path.is_some()
+        || !state.enabled(rules::MISSING_OPERATOR)
+    {
         return vec![];
     }
     let ranges = range_for_rest_of_name(key_val);
@@ -113,7 +204,7 @@ fn noop_but_mm(key_val: &Ranged<KeyVal>, state: &LinterState) -> Vec<Diagnostic>
                 },
                 message: "Expected operator here".to_owned(),
             }]),
-            ..Default::default()
+            source: Some(rules::MISSING_OPERATOR.to_owned()),
         });
     }
     if !diagnostics.is_empty() {
@@ -121,8 +212,58 @@ fn noop_but_mm(key_val: &Ranged<KeyVal>, state: &LinterState) -> Vec<Diagnostic>
             range: key_val.get_range().to_start(),
             severity: Some(crate::parser::Severity::Hint),
             message: "This key contains MM, but has no operator".to_owned(),
+            source: Some(rules::MISSING_OPERATOR.to_owned()),
             ..Default::default()
         });
     }
     diagnostics
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::linter::{diagnostics_for, rules};
+
+    #[test]
+    fn test_tab_in_value() {
+        let diagnostics = diagnostics_for("PART\n{\n\tkey = a\tb\n}\n", rules::TAB_IN_VALUE);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(crate::parser::Severity::Info));
+        assert_eq!(diagnostics[0].range, crate::parser::Range::new(3, 8, 3, 11));
+
+        assert!(diagnostics_for("PART\n{\n\tkey = a b\n}\n", rules::TAB_IN_VALUE).is_empty());
+        // Tabs around the value are not part of it
+        assert!(diagnostics_for("PART\n{\n\tkey\t=\tab\t\n}\n", rules::TAB_IN_VALUE).is_empty());
+    }
+
+    #[test]
+    fn test_brace_in_value() {
+        let diagnostics = diagnostics_for("PART\n{\n\tkey = { oops\n}\n", rules::BRACE_IN_VALUE);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].severity,
+            Some(crate::parser::Severity::Warning)
+        );
+        assert_eq!(diagnostics[0].range, crate::parser::Range::new(3, 8, 3, 14));
+
+        assert!(diagnostics_for("PART\n{\n\tkey = val\n}\n", rules::BRACE_IN_VALUE).is_empty());
+    }
+
+    #[test]
+    fn test_malformed_value_reference() {
+        let rule = rules::MALFORMED_VALUE_REFERENCE;
+        assert!(diagnostics_for("@PART\n{\n\t@key = #$../other$\n}\n", rule).is_empty());
+        assert!(diagnostics_for("@PART\n{\n\t@key = #$a$ #$/b/c$\n}\n", rule).is_empty());
+
+        let diagnostics = diagnostics_for("@PART\n{\n\t@key = #$oops\n}\n", rule);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(crate::parser::Severity::Info));
+        assert_eq!(diagnostics[0].range, crate::parser::Range::new(3, 9, 3, 15));
+
+        let diagnostics = diagnostics_for("@PART\n{\n\t@key = a #$$\n}\n", rule);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].range,
+            crate::parser::Range::new(3, 11, 3, 14)
+        );
+    }
+}
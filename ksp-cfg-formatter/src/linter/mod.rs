@@ -1,73 +1,178 @@
-use crate::parser::{NodeItem, Range, Severity};
-use std::fmt::Display;
+use crate::parser::NodeItem;
+
+pub use crate::diagnostic::{Diagnostic, Location, RelatedInformation};
 
 mod document;
 mod has;
+mod index;
 mod key_val;
 mod node;
 
-/// Takes a `Document` and lints the AST
-#[must_use]
-pub fn lint_ast(ast: &crate::parser::Document, this_url: Option<url::Url>) -> Vec<Diagnostic> {
-    // Only return the Diagnostic part, and ignore the result at this point
-    ast.lint(&LinterState {
-        this_url,
-        top_level_no_op: None,
-    })
-    .0
+/// Ids of the lint rules. Used as the `source` of the diagnostics from the rule, and to enable or disable rules with a [`LintConfig`]
+pub mod rules {
+    /// An operator is used inside a top level node without an operator
+    pub const UNEXPECTED_OPERATOR: &str = "Unexpected_operator";
+    /// Module Manager syntax is used on a node or key without an operator
+    pub const MISSING_OPERATOR: &str = "Missing_operator";
+    /// Regex-replace is used on a key without the edit operator
+    pub const REGEX_WITHOUT_EDIT: &str = "Regex_without_edit";
+    /// Names separated by `|` in a child node, where they are not interpreted as OR
+    pub const OR_IN_CHILD_NODE: &str = "Or_in_child_node";
+    /// An empty name filter, `node[]`
+    pub const EMPTY_NAME: &str = "Empty_name";
+    /// An empty value in a `:HAS` block, `#key[]`
+    pub const EMPTY_HAS_VALUE: &str = "Empty_has_value";
+    /// A `<` or `>` comparison in a `:HAS` block against a value that is not a number
+    pub const NON_NUMERIC_COMPARISON: &str = "Non_numeric_comparison";
+    /// A deleted node with contents that are ignored
+    pub const DELETE_WITH_CONTENT: &str = "Delete_with_content";
+    /// A top level patch matching every node with `[*]`
+    pub const BROAD_WILDCARD: &str = "Broad_wildcard";
+    /// An index that is not `*` or an integer
+    pub const INVALID_INDEX: &str = "Invalid_index";
+    /// A key that is both assigned and edited with `@` in the same node
+    pub const CREATE_AND_EDIT: &str = "Create_and_edit";
+    /// Indentation mixing tabs and spaces
+    pub const MIXED_INDENTATION: &str = "Mixed_indentation";
+    /// A tab character inside a value
+    pub const TAB_IN_VALUE: &str = "Tab_in_value";
+    /// A top level `@` patch on a single node name without a `:HAS` block, which does nothing if the node does not exist
+    pub const EDIT_BY_NAME: &str = "Edit_by_name";
+    /// A `{` inside a value, likely from a missing line ending before a block
+    pub const BRACE_IN_VALUE: &str = "Brace_in_value";
+    /// A value reference, `#$path$`, that is missing the closing `$` or the path
+    pub const MALFORMED_VALUE_REFERENCE: &str = "Malformed_value_reference";
+    /// Line endings mixing `\r\n` and `\n`. Opt-in
+    pub const MIXED_LINE_ENDINGS: &str = "Mixed_line_endings";
+
+    /// Rules that are disabled unless opted in to with [`LintConfig::opt_in`](super::LintConfig::opt_in)
+    pub const OPT_IN: &[&str] = &[MIXED_LINE_ENDINGS];
 }
 
-#[derive(Clone)]
-struct LinterState {
+/// Decides which lint rules are run, by the ids in [`rules`]. All rules except the ones in [`rules::OPT_IN`] are enabled by default
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::linter::{rules, LintConfig};
+///
+/// let config = LintConfig::default().deny([rules::MIXED_INDENTATION]);
+/// assert!(!config.is_enabled(rules::MIXED_INDENTATION));
+/// assert!(config.is_enabled(rules::INVALID_INDEX));
+/// assert!(!config.is_enabled(rules::MIXED_LINE_ENDINGS));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LintConfig {
+    allowed: Option<Vec<String>>,
+    denied: Vec<String>,
+    opted_in: Vec<String>,
+}
+
+impl LintConfig {
+    /// Only runs the rules with the provided ids, and the rules allowed by earlier calls
+    #[must_use]
+    pub fn allow<S: Into<String>>(mut self, rules: impl IntoIterator<Item = S>) -> Self {
+        self.allowed
+            .get_or_insert_with(Vec::new)
+            .extend(rules.into_iter().map(Into::into));
+        self
+    }
+
+    /// Never runs the rules with the provided ids, even if they are allowed
+    #[must_use]
+    pub fn deny<S: Into<String>>(mut self, rules: impl IntoIterator<Item = S>) -> Self {
+        self.denied.extend(rules.into_iter().map(Into::into));
+        self
+    }
+
+    /// Enables the opt-in rules with the provided ids, without restricting the other rules like [`allow`](Self::allow) does
+    #[must_use]
+    pub fn opt_in<S: Into<String>>(mut self, rules: impl IntoIterator<Item = S>) -> Self {
+        self.opted_in.extend(rules.into_iter().map(Into::into));
+        self
+    }
+
+    /// Checks if the rule with the id `rule` should be run
+    #[must_use]
+    pub fn is_enabled(&self, rule: &str) -> bool {
+        let explicitly_allowed = self
+            .allowed
+            .as_ref()
+            .is_some_and(|allowed| allowed.iter().any(|id| id == rule));
+        let allowed = if rules::OPT_IN.contains(&rule) {
+            explicitly_allowed || self.opted_in.iter().any(|id| id == rule)
+        } else {
+            self.allowed.is_none() || explicitly_allowed
+        };
+        allowed && !self.denied.iter().any(|id| id == rule)
+    }
+}
+
+/// Takes a `Document` and lints the AST. `source` is the text the `Document` was parsed from
+#[must_use]
+pub fn lint_ast(
+    ast: &crate::parser::Document,
+    source: &str,
     this_url: Option<url::Url>,
-    top_level_no_op: Option<Location>,
+) -> Vec<Diagnostic> {
+    lint_ast_with_config(ast, source, this_url, &LintConfig::default())
 }
 
-struct LinterStateResult {
-    top_level_no_op_result: bool,
+/// Takes a `Document` and lints the AST, only running the rules enabled in `config`. `source` is the text the `Document` was parsed from
+#[must_use]
+pub fn lint_ast_with_config(
+    ast: &crate::parser::Document,
+    source: &str,
+    this_url: Option<url::Url>,
+    config: &LintConfig,
+) -> Vec<Diagnostic> {
+    // Only return the Diagnostic part, and ignore the result at this point
+    let mut diagnostics = ast
+        .lint(&LinterState {
+            this_url,
+            top_level_no_op: None,
+            config,
+        })
+        .0;
+    // Checks that can only be done on the source text
+    if config.is_enabled(rules::MIXED_INDENTATION) {
+        diagnostics.extend(document::mixed_indentation(source));
+    }
+    if config.is_enabled(rules::MIXED_LINE_ENDINGS) {
+        diagnostics.extend(document::mixed_line_endings(source));
+    }
+    diagnostics
 }
 
-trait Lintable {
-    fn lint(&self, state: &LinterState) -> (Vec<Diagnostic>, Option<LinterStateResult>);
+#[cfg(test)]
+/// Parses `source`, which must have no errors, and returns the diagnostics from the rule with the id `rule`
+pub(crate) fn diagnostics_for(source: &str, rule: &str) -> Vec<Diagnostic> {
+    let (doc, errors) = crate::parser::parse(source);
+    assert!(errors.is_empty(), "{errors:#?}");
+    lint_ast(&doc, source, None)
+        .into_iter()
+        .filter(|diag| diag.source.as_deref() == Some(rule))
+        .collect()
 }
 
-/// Struct for a diagnostic message, like an error or warning
-#[derive(Debug, Default)]
-pub struct Diagnostic {
-    /// The text range the diagnostic covers
-    pub range: Range,
-    /// The severity of the diagnostic
-    pub severity: Option<Severity>,
-    /// The message provided as an explanation for the diagnostic
-    pub message: String,
-    /// The source text causing the diagnostic
-    pub source: Option<String>,
-    /// Any related information to the diagnostic, if applicable
-    pub related_information: Option<Vec<RelatedInformation>>,
+#[derive(Clone)]
+struct LinterState<'a> {
+    this_url: Option<url::Url>,
+    top_level_no_op: Option<Location>,
+    config: &'a LintConfig,
 }
 
-impl Display for Diagnostic {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.range, self.message)
+impl<'a> LinterState<'a> {
+    fn enabled(&self, rule: &str) -> bool {
+        self.config.is_enabled(rule)
     }
 }
 
-/// Information relating to another diagnostic
-#[derive(Clone, Debug)]
-pub struct RelatedInformation {
-    /// The message provided for the related info
-    pub message: String,
-    /// The location of the related info
-    pub location: Location,
+struct LinterStateResult {
+    top_level_no_op_result: bool,
 }
 
-/// A location in a (optional) file
-#[derive(Clone, Debug)]
-pub struct Location {
-    /// An optional Url to the file
-    pub url: Option<url::Url>,
-    /// The range of the location
-    pub range: Range,
+trait Lintable {
+    fn lint(&self, state: &LinterState) -> (Vec<Diagnostic>, Option<LinterStateResult>);
 }
 
 impl<'a> Lintable for NodeItem<'a> {
@@ -76,7 +181,7 @@ impl<'a> Lintable for NodeItem<'a> {
             NodeItem::Node(node) => node.lint(state),
             NodeItem::Comment(comment) => comment.lint(state),
             NodeItem::KeyVal(key_val) => key_val.lint(state),
-            NodeItem::EmptyLine => (vec![], None),
+            NodeItem::EmptyLine(_) => (vec![], None),
             NodeItem::Error(_e) => (vec![], None),
         }
     }
@@ -87,3 +192,36 @@ impl<'a> Lintable for crate::parser::Comment<'a> {
         (vec![], None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn sources(source: &str, config: &LintConfig) -> Vec<String> {
+        let (doc, errors) = parse(source);
+        assert!(errors.is_empty(), "{errors:#?}");
+        lint_ast_with_config(&doc, source, None, config)
+            .into_iter()
+            .map(|diag| diag.source.expect("every rule has an id"))
+            .collect()
+    }
+
+    #[test]
+    fn test_disable_rule() {
+        let source = "PART\n{\n\t@key = val\n\tMODULE[] {}\n}\n";
+        let all = sources(source, &LintConfig::default());
+        assert!(all.iter().any(|id| id == rules::UNEXPECTED_OPERATOR));
+        assert!(all.iter().any(|id| id == rules::EMPTY_NAME));
+
+        let config = LintConfig::default().deny([rules::UNEXPECTED_OPERATOR]);
+        let remaining = sources(source, &config);
+        assert!(!remaining.iter().any(|id| id == rules::UNEXPECTED_OPERATOR));
+        assert!(remaining.iter().any(|id| id == rules::EMPTY_NAME));
+
+        let config = LintConfig::default().allow([rules::UNEXPECTED_OPERATOR]);
+        let only = sources(source, &config);
+        assert!(!only.is_empty());
+        assert!(only.iter().all(|id| id == rules::UNEXPECTED_OPERATOR));
+    }
+}
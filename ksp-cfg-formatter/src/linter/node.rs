@@ -1,6 +1,8 @@
-use crate::parser::{Node, Ranged};
+use crate::parser::{Node, NodeItem, Operator, Ranged};
 
-use super::{Diagnostic, Lintable, LinterState, LinterStateResult, Location, RelatedInformation};
+use super::{
+    rules, Diagnostic, Lintable, LinterState, LinterStateResult, Location, RelatedInformation,
+};
 
 impl<'a> Lintable for crate::parser::Ranged<Node<'a>> {
     fn lint(&self, state: &LinterState) -> (Vec<Diagnostic>, Option<LinterStateResult>) {
@@ -19,6 +21,22 @@ impl<'a> Lintable for crate::parser::Ranged<Node<'a>> {
         }
         // The node has no operator, but uses MM logic in the identifier
         items.append(&mut noop_but_mm(self, state));
+        // The node is deleted, so anything inside it is ignored
+        if state.enabled(rules::DELETE_WITH_CONTENT) {
+            items.extend(delete_with_content(self));
+        }
+        // The patch edits or deletes every node of the type
+        if state.enabled(rules::BROAD_WILDCARD) {
+            items.extend(wildcard_without_filter(self));
+        }
+        // The patch edits a single node by name, which might not exist
+        if state.enabled(rules::EDIT_BY_NAME) {
+            items.extend(edit_by_name(self));
+        }
+        // A key is both created and edited in the same node
+        if state.enabled(rules::CREATE_AND_EDIT) {
+            items.append(&mut create_and_edit(self, state));
+        }
 
         let mut state: LinterState = state.clone();
         // Check for operators in nodes that do not have any operators
@@ -32,16 +50,21 @@ impl<'a> Lintable for crate::parser::Ranged<Node<'a>> {
 
         // Check for an empty name filter "node[]"
         if let Some(name) = &self.name {
-            if name.is_empty() {
+            if name.is_empty() && state.enabled(rules::EMPTY_NAME) {
                 items.push(Diagnostic {
                     range: name.get_range(),
                     severity: Some(crate::parser::Severity::Info),
                     message: "Expected Name".to_owned(),
+                    source: Some(rules::EMPTY_NAME.to_owned()),
                     ..Default::default()
                 });
             }
         }
 
+        if let Some(index) = &self.index {
+            items.append(&mut index.lint(&state).0);
+        }
+
         if let Some(has) = &self.has {
             let (mut diagnostics, _res) = has.lint(&state);
             items.append(&mut diagnostics);
@@ -51,7 +74,7 @@ impl<'a> Lintable for crate::parser::Ranged<Node<'a>> {
             let (mut diagnostics, res) = statement.lint(&state);
             items.append(&mut diagnostics);
             // take info from linter results and merge into this linter result
-            result.top_level_no_op_result |= res.map_or(false, |res| res.top_level_no_op_result);
+            result.top_level_no_op_result |= res.is_some_and(|res| res.top_level_no_op_result);
         }
 
         // Add hint diagnostics to aid hints found in block statements
@@ -78,6 +101,7 @@ fn top_level_no_op_hint(
             severity: Some(crate::parser::Severity::Hint),
             message: "This node has no operator, but contains something that does have an operator"
                 .to_owned(),
+            source: Some(rules::UNEXPECTED_OPERATOR.to_owned()),
             ..Default::default()
         })
     } else {
@@ -87,15 +111,19 @@ fn top_level_no_op_hint(
 
 fn or_in_child_node(
     node: &Node<'_>,
-    _state: &LinterState,
+    state: &LinterState,
     _result: &mut LinterStateResult,
 ) -> Option<Diagnostic> {
+    if !state.enabled(rules::OR_IN_CHILD_NODE) {
+        return None;
+    }
     if let Some(name) = &node.name {
         if name.len() > 1 && !node.top_level() {
             Some(Diagnostic {
             range: name.get_range(),
             severity: Some(crate::parser::Severity::Warning),
             message: "names separated by '|' is only interpreted as OR in a top level node. Here, it's interpreted literally.".to_owned(),
+            source: Some(rules::OR_IN_CHILD_NODE.to_owned()),
             ..Default::default()
         })
         } else {
@@ -111,18 +139,24 @@ fn op_in_noop(
     state: &LinterState,
     result: &mut LinterStateResult,
 ) -> Option<Diagnostic> {
+    if !state.enabled(rules::UNEXPECTED_OPERATOR) {
+        return None;
+    }
     if let Some(top_level_no_op) = &state.top_level_no_op {
         if let Some(operator) = &node.operator {
             result.top_level_no_op_result = true;
             Some(Diagnostic {
                 range: operator.get_range(),
                 severity: Some(crate::parser::Severity::Warning),
-                message: "Node has operator, but top level does not!".to_owned(),
+                message: format!(
+                    "Node has operator `{}`, but top level does not!",
+                    operator.as_ref()
+                ),
                 related_information: Some(vec![super::RelatedInformation {
                     location: top_level_no_op.clone(),
                     message: "This is where it happened".to_owned(),
                 }]),
-                source: Some("Unexpected_operator".to_owned()),
+                source: Some(rules::UNEXPECTED_OPERATOR.to_owned()),
             })
         } else {
             None
@@ -132,6 +166,126 @@ fn op_in_noop(
     }
 }
 
+fn delete_with_content(node: &Node) -> Option<Diagnostic> {
+    if !matches!(
+        node.operator.as_deref(),
+        Some(Operator::Delete | Operator::DeleteAlt)
+    ) {
+        return None;
+    }
+    let range = node
+        .block
+        .iter()
+        .filter(|item| matches!(item, NodeItem::KeyVal(_) | NodeItem::Node(_)))
+        .map(NodeItem::range)
+        .reduce(|a, b| a + b)?;
+    Some(Diagnostic {
+        range,
+        severity: Some(crate::parser::Severity::Info),
+        message: format!(
+            "The node is deleted, so the contents of the block are ignored{}",
+            if node.needs.is_some() {
+                ". The `:NEEDS` block only decides if the node is deleted"
+            } else {
+                ""
+            }
+        ),
+        source: Some(rules::DELETE_WITH_CONTENT.to_owned()),
+        ..Default::default()
+    })
+}
+
+fn wildcard_without_filter(node: &Node) -> Option<Diagnostic> {
+    let name = node.name.as_ref()?;
+    if !node.top_level()
+        || node.has.is_some()
+        || name.as_slice() != ["*"]
+        || !(node.is_edit() || node.is_delete())
+    {
+        return None;
+    }
+    Some(Diagnostic {
+        range: name.get_range(),
+        severity: Some(crate::parser::Severity::Warning),
+        message: format!(
+            "`{}[*]` matches every `{}` node. Consider narrowing it down with a `:HAS` block",
+            node.identifier, node.identifier
+        ),
+        source: Some(rules::BROAD_WILDCARD.to_owned()),
+        ..Default::default()
+    })
+}
+
+fn edit_by_name(node: &Node) -> Option<Diagnostic> {
+    let name = node.name.as_ref()?;
+    let [single] = name.as_slice() else {
+        return None;
+    };
+    // Wildcards match any existing nodes, so they are not meant to create one
+    if !node.top_level()
+        || node.has.is_some()
+        || node.path.is_some()
+        || !matches!(node.operator.as_deref(), Some(Operator::Edit))
+        || single.is_empty()
+        || single.contains(['*', '?'])
+    {
+        return None;
+    }
+    Some(Diagnostic {
+        range: name.get_range(),
+        severity: Some(crate::parser::Severity::Hint),
+        message: format!(
+            "`@` only edits an existing `{}[{single}]`. If it is a new node, copy one with `+` or create it without an operator",
+            node.identifier
+        ),
+        source: Some(rules::EDIT_BY_NAME.to_owned()),
+        ..Default::default()
+    })
+}
+
+fn create_and_edit(node: &Node, state: &LinterState) -> Vec<Diagnostic> {
+    let key_vals = node
+        .block
+        .iter()
+        .filter_map(|item| match item {
+            NodeItem::KeyVal(kv) if kv.path.is_none() => Some(kv),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut diagnostics = vec![];
+    for edit in key_vals
+        .iter()
+        .filter(|kv| matches!(kv.operator.as_deref(), Some(Operator::Edit)))
+    {
+        let Some(create) = key_vals.iter().find(|kv| {
+            *kv.key == *edit.key
+                && kv
+                    .operator
+                    .as_deref()
+                    .is_none_or(|op| matches!(op, Operator::None))
+        }) else {
+            continue;
+        };
+        diagnostics.push(Diagnostic {
+            range: edit.key.get_range(),
+            severity: Some(crate::parser::Severity::Info),
+            message: format!(
+                "`{key}` is both assigned and edited in this node. `{key} = ...` adds a new value, while `@{key} = ...` edits an existing one",
+                key = *edit.key
+            ),
+            source: Some(rules::CREATE_AND_EDIT.to_owned()),
+            related_information: Some(vec![RelatedInformation {
+                location: Location {
+                    range: create.key.get_range(),
+                    url: state.this_url.clone(),
+                },
+                message: "The value is added here".to_owned(),
+            }]),
+        });
+    }
+    diagnostics
+}
+
 // TODO: Are there some MM things that are allowed?
 fn range_for_rest_of_id(node: &Node) -> Vec<crate::parser::Range> {
     let mut ranges = vec![];
@@ -155,7 +309,7 @@ fn range_for_rest_of_id(node: &Node) -> Vec<crate::parser::Range> {
 }
 
 fn noop_but_mm(node: &Ranged<Node>, state: &LinterState) -> Vec<Diagnostic> {
-    if node.operator.is_some() || node.path.is_some() {
+    if node.operator.is_some() || node.path.is_some() || !state.enabled(rules::MISSING_OPERATOR) {
         return vec![];
     }
     let ranges = range_for_rest_of_id(node);
@@ -174,7 +328,7 @@ fn noop_but_mm(node: &Ranged<Node>, state: &LinterState) -> Vec<Diagnostic> {
                 },
                 message: "Expected operator here".to_owned(),
             }]),
-            ..Default::default()
+            source: Some(rules::MISSING_OPERATOR.to_owned()),
         });
     }
     if !diagnostics.is_empty() {
@@ -182,8 +336,117 @@ fn noop_but_mm(node: &Ranged<Node>, state: &LinterState) -> Vec<Diagnostic> {
             range: node.get_range().to_start(),
             severity: Some(crate::parser::Severity::Hint),
             message: "This node identifier contains MM, but has no operator".to_owned(),
+            source: Some(rules::MISSING_OPERATOR.to_owned()),
             ..Default::default()
         });
     }
     diagnostics
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::linter::{diagnostics_for, rules};
+
+    #[test]
+    fn test_delete_with_content() {
+        let diagnostics = diagnostics_for("!PART { key = 1 }\n", rules::DELETE_WITH_CONTENT);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range, crate::parser::Range::new(1, 9, 1, 16));
+        assert!(!diagnostics[0].message.contains(":NEEDS"));
+
+        let diagnostics = diagnostics_for(
+            "!PART:NEEDS[ModX]\n{\n\tkey = 1\n}\n",
+            rules::DELETE_WITH_CONTENT,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains(":NEEDS"));
+    }
+
+    #[test]
+    fn test_broad_wildcard() {
+        let rule = rules::BROAD_WILDCARD;
+        let diagnostics = diagnostics_for("@PART[*] { }\n", rule);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].severity,
+            Some(crate::parser::Severity::Warning)
+        );
+        assert_eq!(diagnostics[0].range, crate::parser::Range::new(1, 6, 1, 9));
+        assert_eq!(diagnostics_for("!PART[*] { }\n", rule).len(), 1);
+
+        assert!(diagnostics_for("@PART[*]:HAS[#key] { }\n", rule).is_empty());
+        assert!(diagnostics_for("@PART[foo*] { }\n", rule).is_empty());
+        assert!(diagnostics_for("+PART[*] { }\n", rule).is_empty());
+        assert!(diagnostics_for("@PART[foo]\n{\n\t@MODULE[*] { }\n}\n", rule).is_empty());
+    }
+
+    #[test]
+    fn test_delete_without_content() {
+        let rule = rules::DELETE_WITH_CONTENT;
+        assert!(diagnostics_for("!PART { }\n", rule).is_empty());
+        assert!(diagnostics_for("!PART\n{\n\t// comment\n}\n", rule).is_empty());
+        assert!(diagnostics_for("@PART { key = 1 }\n", rule).is_empty());
+    }
+
+    #[test]
+    fn test_op_in_noop_names_operator() {
+        for (source, operator) in [
+            ("PART\n{\n\t%MODULE { }\n}\n", "`%`"),
+            ("PART\n{\n\t@MODULE { }\n}\n", "`@`"),
+            ("PART\n{\n\t%key = 1\n}\n", "`%`"),
+        ] {
+            let diagnostics: Vec<_> = diagnostics_for(source, rules::UNEXPECTED_OPERATOR)
+                .into_iter()
+                .filter(|diag| diag.severity == Some(crate::parser::Severity::Warning))
+                .collect();
+            assert_eq!(diagnostics.len(), 1, "{diagnostics:#?}");
+            assert!(
+                diagnostics[0].message.contains(operator),
+                "{}",
+                diagnostics[0].message
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_and_edit() {
+        let rule = rules::CREATE_AND_EDIT;
+        let diagnostics = diagnostics_for("@PART\n{\n\tkey = 1\n\t@key = 2\n}\n", rule);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(crate::parser::Severity::Info));
+        assert_eq!(diagnostics[0].range, crate::parser::Range::new(4, 3, 4, 6));
+        let related = diagnostics[0].related_information.as_ref().unwrap();
+        assert_eq!(
+            related[0].location.range,
+            crate::parser::Range::new(3, 2, 3, 5)
+        );
+
+        assert!(diagnostics_for("@PART\n{\n\tkey = 1\n\t@other = 2\n}\n", rule).is_empty());
+        assert!(diagnostics_for("@PART\n{\n\t%key = 1\n\t@key = 2\n}\n", rule).is_empty());
+        assert!(diagnostics_for("@PART\n{\n\tkey = 1\n\tkey = 2\n}\n", rule).is_empty());
+    }
+
+    #[test]
+    fn test_edit_by_name() {
+        let rule = rules::EDIT_BY_NAME;
+        let diagnostics = diagnostics_for("@PART[foo] { }\n", rule);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(crate::parser::Severity::Hint));
+        assert_eq!(diagnostics[0].range, crate::parser::Range::new(1, 6, 1, 11));
+
+        for source in [
+            "@PART[foo]:HAS[#key] { }\n",
+            "@PART[foo*] { }\n",
+            "@PART[foo|bar] { }\n",
+            "%PART[foo] { }\n",
+            "+PART[foo] { }\n",
+        ] {
+            assert!(diagnostics_for(source, rule).is_empty(), "{source}");
+        }
+        // Child nodes are not checked
+        assert_eq!(
+            diagnostics_for("@PART[foo] { @MODULE[bar] { } }\n", rule).len(),
+            1
+        );
+    }
+}
@@ -1,12 +1,16 @@
 use super::{
+    parse_complete,
     parser_helpers::range_wrap,
-    Ranged, {ASTParse, IResult, LocatedSpan},
+    Error, Ranged, {ASTParse, IResult, LocatedSpan},
 };
 use nom::{branch::alt, bytes::complete::tag, combinator::value};
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 /// Assignment operator in a key-val
-#[derive(Debug, Default, Clone, Copy)]
+///
+/// These are all the assignment operators of Module Manager. There is no operator to remove a value from a list.
+/// Values are deleted with the delete [`super::Operator`] on the key instead, like `!key = dummy` or `-key,* = dummy`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum AssignmentOperator {
     /// Default assignment, `=`
     #[default]
@@ -19,7 +23,7 @@ pub enum AssignmentOperator {
     Add,
     /// Decrement the variable by the value, `-=`
     Subtract,
-    /// Raise the variable by the value, `!=`
+    /// Raise the variable to the power of the value, `!=`. Not a comparison or a deletion
     Power,
     /// Regex operation, `^=`
     RegexReplace,
@@ -38,6 +42,17 @@ impl Display for AssignmentOperator {
         }
     }
 }
+impl FromStr for AssignmentOperator {
+    type Err = Error;
+
+    /// Parses an assignment operator, e.g. `"+=".parse::<AssignmentOperator>()`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_complete::<AssignmentOperator>(s, "assignment operator")
+            .map(|op| *op)
+            .map_err(|mut errors| errors.remove(0))
+    }
+}
+
 impl ASTParse<'_> for AssignmentOperator {
     fn parse(input: LocatedSpan) -> IResult<Ranged<AssignmentOperator>> {
         range_wrap(alt((
@@ -51,3 +66,67 @@ impl ASTParse<'_> for AssignmentOperator {
         )))(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let operators = [
+            ("=", AssignmentOperator::Assign),
+            ("*=", AssignmentOperator::Multiply),
+            ("/=", AssignmentOperator::Divide),
+            ("+=", AssignmentOperator::Add),
+            ("-=", AssignmentOperator::Subtract),
+            ("!=", AssignmentOperator::Power),
+            ("^=", AssignmentOperator::RegexReplace),
+        ];
+        for (input, expected) in operators {
+            assert_eq!(input.parse::<AssignmentOperator>(), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_from_str_error() {
+        assert_eq!(
+            "==".parse::<AssignmentOperator>().unwrap_err().message,
+            "unexpected `=` after assignment operator"
+        );
+        assert!("~=".parse::<AssignmentOperator>().is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        use crate::parser::{parse_key_val, ASTPrint, Operator, PrintSettings};
+
+        for (input, expected) in [
+            ("@key = value", AssignmentOperator::Assign),
+            ("@key *= 2", AssignmentOperator::Multiply),
+            ("@key /= 2", AssignmentOperator::Divide),
+            ("@key += 2", AssignmentOperator::Add),
+            ("@key -= 2", AssignmentOperator::Subtract),
+            ("@key != 2", AssignmentOperator::Power),
+            ("@key ^= :a:b:", AssignmentOperator::RegexReplace),
+        ] {
+            let key_val = parse_key_val(input).unwrap_or_else(|err| panic!("{input}: {err:#?}"));
+            assert_eq!(*key_val.assignment_operator, expected);
+            assert_eq!(
+                key_val.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+                format!("{input}\n")
+            );
+        }
+        // Values are deleted with the operator on the key
+        for input in ["!key = dummy", "-key,* = dummy"] {
+            let key_val = parse_key_val(input).unwrap_or_else(|err| panic!("{input}: {err:#?}"));
+            assert!(matches!(
+                key_val.operator.as_deref(),
+                Some(Operator::Delete | Operator::DeleteAlt)
+            ));
+            assert_eq!(
+                key_val.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+                format!("{input}\n")
+            );
+        }
+    }
+}
@@ -1,18 +1,24 @@
 use super::{
+    codes,
     parser_helpers::{range_wrap, ws},
-    ASTPrint, Ranged, {ASTParse, IResult, LocatedSpan},
+    ASTPrint, Error, PrintSettings, Range, Ranged, Severity, {ASTParse, IResult, LocatedSpan},
 };
+use std::borrow::Cow;
+
 use nom::{
-    bytes::complete::{is_not, tag},
+    branch::alt,
+    bytes::complete::{is_not, tag, take_until},
     combinator::{map, opt, recognize},
-    sequence::pair,
+    sequence::{pair, tuple},
 };
 
 /// A comment in the file. Includes the leading whitespace and `//`
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Comment<'a> {
     /// Text of the comment, including leading whitespace and `//`
     pub text: &'a str,
+    /// The whitespace before the comment, as written, when it is the first thing on its line inside a node. `None` for other comments
+    pub indentation: Option<&'a str>,
 }
 
 impl<'a> ASTPrint for Comment<'a> {
@@ -21,18 +27,149 @@ impl<'a> ASTPrint for Comment<'a> {
         depth: usize,
         indentation: &str,
         line_ending: &str,
-        _: Option<bool>,
+        settings: &PrintSettings,
     ) -> String {
-        format!("{}{}{}", indentation.repeat(depth), self.text, line_ending)
+        format!(
+            "{}{}{}",
+            indentation.repeat(depth),
+            self.print_text(settings),
+            line_ending
+        )
+    }
+}
+
+impl<'a> Comment<'a> {
+    /// The text of the comment as it should be printed with the provided settings.
+    ///
+    /// If `normalize_comments` is set, there is exactly one space between `//` and the text of the comment, and trailing whitespace is removed.
+    /// The spacing of comments starting with `///` or `//!`, like dividers, and empty comments is left unchanged
+    #[must_use]
+    pub fn print_text(&self, settings: &PrintSettings) -> Cow<'a, str> {
+        if !settings.normalize_comments {
+            return Cow::Borrowed(self.text);
+        }
+        let text = self.text.trim_end_matches([' ', '\t']);
+        let Some((leading, content)) = text.split_once("//") else {
+            return Cow::Borrowed(text);
+        };
+        let content = content.trim_start_matches([' ', '\t']);
+        if content.is_empty() || content.starts_with(['/', '!']) {
+            return Cow::Borrowed(text);
+        }
+        Cow::Owned(format!("{leading}// {content}"))
     }
 }
 
 impl<'a> ASTParse<'a> for Comment<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<Comment<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<Comment<'a>>> {
         let comment = recognize(ws(pair(tag("//"), opt(is_not("\r\n")))));
 
         range_wrap(map(comment, |inner: LocatedSpan| Comment {
             text: inner.fragment(),
+            indentation: None,
         }))(input)
     }
 }
+
+/// Block comments, `/* */`, are not supported in KSP cfg files. Consumes the block comment, or the rest of the line if it is not closed on the same line, and reports an error
+pub(crate) fn block_comment(input: LocatedSpan<'_>) -> IResult<'_, Ranged<&str>> {
+    let (rest, comment) = recognize(pair(
+        tag("/*"),
+        alt((
+            recognize(tuple((take_until_on_line("*/"), tag("*/")))),
+            take_until_line_end,
+        )),
+    ))(input)?;
+    rest.extra.report_error(Error {
+        severity: Severity::Error,
+        code: codes::BLOCK_COMMENT,
+        range: Range::from(comment.clone()),
+        source: (*comment.fragment()).to_owned(),
+        message: "block comments are not supported in KSP cfg".to_owned(),
+        context: None,
+    });
+    Ok((rest, Ranged::from(comment)))
+}
+
+fn take_until_line_end(input: LocatedSpan<'_>) -> IResult<'_, LocatedSpan<'_>> {
+    recognize(opt(is_not("\r\n")))(input)
+}
+
+/// Takes everything until `pattern`, failing if the end of the line is reached first
+fn take_until_on_line(
+    pattern: &'static str,
+) -> impl FnMut(LocatedSpan<'_>) -> IResult<'_, LocatedSpan<'_>> {
+    move |input| {
+        let (rest, taken) = take_until(pattern)(input.clone())?;
+        if taken.fragment().contains(['\r', '\n']) {
+            return Err(nom::Err::Error(nom::error::Error {
+                input,
+                code: nom::error::ErrorKind::TakeUntil,
+            }));
+        }
+        Ok((rest, taken))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_comments() {
+        let settings = PrintSettings::new(None).normalize_comments(true);
+        for (text, expected) in [
+            ("//x", "// x"),
+            ("// x", "// x"),
+            ("//   x  y ", "// x  y"),
+            ("// foo   ", "// foo"),
+            ("//// divider \t", "//// divider"),
+            ("//  ", "//"),
+            ("\t//\tx", "\t// x"),
+            ("//// divider", "//// divider"),
+            ("//!x", "//!x"),
+            ("//", "//"),
+        ] {
+            assert_eq!(
+                Comment {
+                    text,
+                    indentation: None
+                }
+                .print_text(&settings),
+                expected
+            );
+        }
+        let settings = PrintSettings::new(None);
+        assert_eq!(
+            Comment {
+                text: "//x",
+                indentation: None
+            }
+            .print_text(&settings),
+            "//x"
+        );
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let input = "/* header */\nnode\n{\n\t/* not closed\n\tkey = val\n}\n";
+        let (doc, errors) = crate::parser::parse(input);
+        assert_eq!(errors.len(), 2, "{errors:#?}");
+        for error in &errors {
+            assert_eq!(error.severity, Severity::Error);
+            assert_eq!(error.message, "block comments are not supported in KSP cfg");
+        }
+        assert_eq!(errors[0].range, Range::new(1, 1, 1, 13));
+        // Recovers at the end of the line
+        assert_eq!(errors[1].range, Range::new(4, 2, 4, 15));
+        let node = doc
+            .statements
+            .iter()
+            .find_map(|item| match item {
+                crate::parser::DocItem::Node(node) => Some(node),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(node.iter_keyvals().count(), 1);
+    }
+}
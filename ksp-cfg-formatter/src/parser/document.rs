@@ -8,61 +8,273 @@ use nom::{
 };
 
 use super::{
+    block_comment, codes,
     parser_helpers::{
         debug_fn, empty_line, error_till, expect, ignore_line_ending, non_empty, range_wrap, ws,
     },
-    ASTPrint, Comment, Node, Ranged, {ASTParse, IResult, LocatedSpan},
+    ASTPrint, Comment, Node, NodeItem, Pass, Path, PrintSettings, Range, Ranged, State,
+    {ASTParse, IResult, LocatedSpan},
 };
 
 /// Enum for the different items that can exist in a document/node
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 pub enum DocItem<'a> {
     /// A node
     Node(Ranged<Node<'a>>),
     /// A Comment
     Comment(Ranged<Comment<'a>>),
-    /// An empty line
-    EmptyLine,
+    /// An empty line, with an empty range at the start of the line
+    EmptyLine(Range),
     /// An error instead of a doc item
     Error(Ranged<&'a str>),
 }
+
+impl<'a> DocItem<'a> {
+    /// Returns the range covered by the item. An `EmptyLine` returns an empty range at the start of the line
+    #[must_use]
+    pub fn range(&self) -> Range {
+        match self {
+            Self::Node(node) => node.get_range(),
+            Self::Comment(comment) => comment.get_range(),
+            Self::EmptyLine(range) => *range,
+            Self::Error(error) => error.get_range(),
+        }
+    }
+    /// Compares two items, ignoring the ranges. See [`Node::eq_ignore_ranges()`]
+    #[must_use]
+    pub fn eq_ignore_ranges(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Node(a), Self::Node(b)) => a.eq_ignore_ranges(b),
+            (Self::Comment(a), Self::Comment(b)) => a.text == b.text,
+            (Self::EmptyLine(_), Self::EmptyLine(_)) => true,
+            (Self::Error(a), Self::Error(b)) => **a == **b,
+            _ => false,
+        }
+    }
+}
 impl<'a> ASTPrint for DocItem<'a> {
     fn ast_print(
         &self,
         depth: usize,
         indentation: &str,
         line_ending: &str,
-        should_collapse: Option<bool>,
+        settings: &PrintSettings,
     ) -> String {
         match self {
-            Self::Node(node) => node.ast_print(depth, indentation, line_ending, should_collapse),
-            Self::Comment(comment) => {
-                comment.ast_print(depth, indentation, line_ending, should_collapse)
-            }
-            Self::EmptyLine => line_ending.to_owned(),
+            Self::Node(node) => node.ast_print(depth, indentation, line_ending, settings),
+            Self::Comment(comment) => comment.ast_print(depth, indentation, line_ending, settings),
+            Self::EmptyLine(_) => line_ending.to_owned(),
             Self::Error(a) => a.to_string(),
         }
     }
 }
 
+/// Counts of the different items in a `Document`. See [`Document::stats()`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DocStats {
+    /// Number of nodes, including child nodes
+    pub nodes: usize,
+    /// Number of assignments
+    pub keyvals: usize,
+    /// Number of comments, including comments after node identifiers, closing brackets and assignments
+    pub comments: usize,
+    /// Number of empty lines. The line ending after an item that failed to parse is not counted
+    pub empty_lines: usize,
+    /// Number of items that failed to parse
+    pub errors: usize,
+}
+
 /// Contains all the statements of a file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Document<'a> {
     /// List of all the statements. Can be `Node`s, `Comment`s, or `EmptyLine`s
     pub statements: Vec<DocItem<'a>>,
 }
 
+impl<'a> Document<'a> {
+    /// Counts the nodes, assignments, comments, empty lines and errors in the document, including inside child nodes
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::parse;
+    ///
+    /// let (doc, _errors) = parse("node { key = val }\n");
+    /// let stats = doc.stats();
+    /// assert_eq!((stats.nodes, stats.keyvals), (1, 1));
+    /// ```
+    #[must_use]
+    pub fn stats(&self) -> DocStats {
+        let mut stats = DocStats::default();
+        let mut after_error = false;
+        for item in &self.statements {
+            match item {
+                DocItem::Node(node) => node.add_stats(&mut stats),
+                DocItem::Comment(_) => stats.comments += 1,
+                // The line ending after an error is parsed as an empty line
+                DocItem::EmptyLine(_) if after_error => (),
+                DocItem::EmptyLine(_) => stats.empty_lines += 1,
+                DocItem::Error(_) => stats.errors += 1,
+            }
+            after_error = matches!(item, DocItem::Error(_));
+        }
+        stats
+    }
+
+    /// Prints the document as an S-expression, like `(document (node "@PART" [foo] (keyval "mass" "=" "1")))`.
+    ///
+    /// This is meant for debugging the parser and for bug reports, not for formatting.
+    /// Comments attached to nodes and assignments are left out, while comments on their own line are included
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::parse;
+    ///
+    /// let (doc, _errors) = parse("@PART[foo] { mass = 1 }\n");
+    /// assert_eq!(doc.to_sexp(), r#"(document (node "@PART" [foo] (keyval "mass" "=" "1")))"#);
+    /// ```
+    #[must_use]
+    pub fn to_sexp(&self) -> String {
+        let mut parts = vec!["document".to_owned()];
+        for item in &self.statements {
+            parts.push(match item {
+                DocItem::Node(node) => node.to_sexp(),
+                DocItem::Comment(comment) => format!("(comment {:?})", comment.text.trim_start()),
+                DocItem::EmptyLine(_) => "(empty_line)".to_owned(),
+                DocItem::Error(error) => format!("(error {:?})", **error),
+            });
+        }
+        format!("({})", parts.join(" "))
+    }
+
+    /// Compares two documents, ignoring the ranges of all items. Unlike `==`, this is true for the same content parsed from differently formatted text
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::parse;
+    ///
+    /// let (a, _errors) = parse("node\n{\n\tkey = val\n}\n");
+    /// let (b, _errors) = parse("node\n{\n    key   =   val\n}\n");
+    /// assert_ne!(a, b);
+    /// assert!(a.eq_ignore_ranges(&b));
+    /// ```
+    #[must_use]
+    pub fn eq_ignore_ranges(&self, other: &Self) -> bool {
+        self.statements.len() == other.statements.len()
+            && self
+                .statements
+                .iter()
+                .zip(&other.statements)
+                .all(|(a, b)| a.eq_ignore_ranges(b))
+    }
+
+    /// The header and range of each top level node, in the order of the document. A lighter alternative to a full symbol tree, e.g. for an outline. See [`Node::header()`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::{parse, Range};
+    ///
+    /// let (doc, _errors) = parse("@PART[foo] { mass = 1 }\n");
+    /// assert_eq!(doc.top_level_ranges(), vec![("@PART[foo]".to_owned(), Range::new(1, 1, 1, 24))]);
+    /// ```
+    #[must_use]
+    pub fn top_level_ranges(&self) -> Vec<(String, Range)> {
+        self.statements
+            .iter()
+            .filter_map(|item| match item {
+                DocItem::Node(node) => Some((node.header(), node.get_range())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Collects every pass referring to a mod, `:BEFORE`, `:FOR`, `:AFTER` and `:LAST`, from all nodes in the document, including child nodes
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::{parse, Pass};
+    ///
+    /// let (doc, _errors) = parse("@PART:FOR[MyMod] {}\n");
+    /// let passes: Vec<Pass> = doc.pass_references().into_iter().map(|(pass, _range)| pass).collect();
+    /// assert_eq!(passes, vec![Pass::For("MyMod")]);
+    /// ```
+    #[must_use]
+    pub fn pass_references(&self) -> Vec<(Pass<'a>, Range)> {
+        let mut references = vec![];
+        for item in &self.statements {
+            if let DocItem::Node(node) = item {
+                node.pass_references(&mut references);
+            }
+        }
+        references
+    }
+
+    /// Finds the node at `path`, like `PART[foo]/MODULE[bar]`, starting from the top level of the document.
+    ///
+    /// Each segment matches the first node with the same type and name, see [`super::PathSegment::matches()`].
+    /// Returns `None` if the path can't be parsed, contains `..`, or no node matches
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::parse;
+    ///
+    /// let (doc, _errors) = parse("PART\n{\n\tname = foo\n\tMODULE[bar] {}\n}\n");
+    /// assert!(doc.node_at_path("PART/MODULE[bar]").is_some());
+    /// assert!(doc.node_at_path("PART/MODULE[baz]").is_none());
+    /// ```
+    #[must_use]
+    pub fn node_at_path(&self, path: &str) -> Option<&Node<'a>> {
+        // Every segment of a parsed path ends with `/`
+        let text = format!("{}/", path.trim_end_matches('/'));
+        let (rest, path) = Path::parse(LocatedSpan::new_extra(&text, State::default())).ok()?;
+        if !rest.is_empty() {
+            return None;
+        }
+        let (first, segments) = path.segments.split_first()?;
+        let mut node = self.statements.iter().find_map(|item| match item {
+            DocItem::Node(node) if first.matches(node) => Some(&**node),
+            _ => None,
+        })?;
+        for segment in segments {
+            node = node.block.iter().find_map(|item| match item {
+                NodeItem::Node(node) if segment.matches(node) => Some(&**node),
+                _ => None,
+            })?;
+        }
+        Some(node)
+    }
+}
+
+impl<'a> Document<'a> {
+    /// Checks if the item at `index` directly follows a node, and starts a node, possibly with comments above it
+    fn needs_blank_line_before(&self, index: usize) -> bool {
+        let follows_node = index
+            .checked_sub(1)
+            .is_some_and(|prev| matches!(self.statements[prev], DocItem::Node(_)));
+        let starts_node = matches!(
+            self.statements[index..]
+                .iter()
+                .find(|item| !matches!(item, DocItem::Comment(_))),
+            Some(DocItem::Node(_))
+        );
+        follows_node && starts_node
+    }
+}
+
 impl<'a> ASTPrint for Document<'a> {
     fn ast_print(
         &self,
         depth: usize,
         indentation: &str,
         line_ending: &str,
-        should_collapse: Option<bool>,
+        settings: &PrintSettings,
     ) -> String {
         let mut output = String::new();
-        for item in &self.statements {
-            output.push_str(&item.ast_print(depth, indentation, line_ending, should_collapse));
+        for (i, item) in self.statements.iter().enumerate() {
+            if settings.blank_line_between_top_level && self.needs_blank_line_before(i) {
+                output.push_str(line_ending);
+            }
+            output.push_str(&item.ast_print(depth, indentation, line_ending, settings));
         }
         output
     }
@@ -73,24 +285,32 @@ pub fn source_file(input: LocatedSpan) -> IResult<Ranged<Document>> {
     let doc = alt((
         Document::parse,
         map(take(0usize), |_| {
-            Ranged::new(Document { statements: vec![] }, super::Range::default())
+            Ranged::new(Document { statements: vec![] }, Range::default())
         }),
     ));
     // Emitt an error if the whole input is not consumed
-    terminated(doc, preceded(expect(not(anychar), "expected EOF"), rest))(input)
+    terminated(
+        doc,
+        preceded(
+            expect(not(anychar), codes::UNEXPECTED_TEXT, "expected EOF"),
+            rest,
+        ),
+    )(input)
 }
 
 impl<'a> ASTParse<'a> for Document<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<Document<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<Document<'a>>> {
         range_wrap(map(
             preceded(
                 tuple((opt(tag("\u{feff}")), multispace0)),
                 many_till(
                     alt((
                         map(ignore_line_ending(ws(Comment::parse)), DocItem::Comment),
-                        map(alt((empty_line, map(pair(space1, eof), |_| ()))), |()| {
-                            DocItem::EmptyLine
-                        }),
+                        map(ws(block_comment), DocItem::Error),
+                        map(
+                            range_wrap(alt((empty_line, map(pair(space1, eof), |_| ())))),
+                            |empty| DocItem::EmptyLine(empty.get_range().to_start()),
+                        ),
                         map(ignore_line_ending(ws(Node::parse)), DocItem::Node),
                         // If none of the above succeeded, consume the line as an error and try again
                         debug_fn(
@@ -123,7 +343,10 @@ mod tests {
         let res = Document::parse(LocatedSpan::new_extra(input, State::default()));
 
         match res {
-            Ok(it) => assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", Some(true))),
+            Ok(it) => assert_eq!(
+                input,
+                it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(Some(true)))
+            ),
             Err(err) => panic!("{}", err),
         }
     }
@@ -133,7 +356,10 @@ mod tests {
         let res = Document::parse(LocatedSpan::new_extra(input, State::default()));
 
         match res {
-            Ok(it) => assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", Some(true))),
+            Ok(it) => assert_eq!(
+                input,
+                it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(Some(true)))
+            ),
             Err(err) => panic!("{}", err),
         }
     }
@@ -143,8 +369,149 @@ mod tests {
         let res = Document::parse(LocatedSpan::new_extra(input, State::default()));
 
         match res {
-            Ok(it) => assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", Some(true))),
+            Ok(it) => assert_eq!(
+                input,
+                it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(Some(true)))
+            ),
             Err(err) => panic!("{}", err),
         }
     }
+    #[test]
+    fn test_empty_document() {
+        for input in ["", "\n", "\r\n\r\n", "  \n\t\n", "   "] {
+            let (doc, errors) = crate::parser::parse(input);
+            assert!(errors.is_empty(), "{input:?}: {errors:#?}");
+            assert!(doc.statements.is_empty(), "{input:?}: {doc:#?}");
+        }
+        // The fallback of `source_file` gives the same document as parsing the empty input
+        let (_, fallback) = source_file(LocatedSpan::new_extra("", State::default())).unwrap();
+        assert_eq!(fallback.inner, Document { statements: vec![] });
+        assert_eq!(
+            fallback.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+            ""
+        );
+    }
+    #[test]
+    fn test_doc_item_ranges() {
+        let input = "// c\n\nnode {}\ngarbage\n";
+        let (doc, _errors) = crate::parser::parse(input);
+        let ranges: Vec<Range> = doc.statements.iter().map(DocItem::range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                // Comment
+                Range::new(1, 1, 1, 5),
+                // Empty line
+                Range::new(2, 1, 2, 1),
+                // Node
+                Range::new(3, 1, 3, 8),
+                // Error
+                Range::new(4, 1, 4, 8),
+                // Line ending after the error
+                Range::new(4, 8, 4, 8),
+            ]
+        );
+    }
+    #[test]
+    fn test_doc_eq() {
+        let input =
+            "@PART[foo]:HAS[#key[val]]:NEEDS[mod]\n{\n\t@key,0 = val // comment\n\tnode {}\n}\n";
+        let (doc_1, _errors) = crate::parser::parse(input);
+        let (doc_2, _errors) = crate::parser::parse(input);
+        assert_eq!(doc_1, doc_2);
+
+        let other_input = input.replace("@key", "%key");
+        let (doc_3, _errors) = crate::parser::parse(&other_input);
+        assert_ne!(doc_1, doc_3);
+    }
+
+    #[test]
+    fn test_pass_references() {
+        let input =
+            "@PART:FOR[A]\n{\n\t@MODULE:FIRST {}\n\tinner:AFTER[B] { }\n}\n@PART:FINAL {}\n";
+        let (doc, errors) = crate::parser::parse(input);
+        assert!(errors.is_empty(), "{errors:#?}");
+        assert_eq!(
+            doc.pass_references(),
+            vec![
+                (Pass::For("A"), Range::new(1, 6, 1, 13)),
+                (Pass::After("B"), Range::new(4, 7, 4, 16)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_node_at_path() {
+        let input = "PART\n{\n\tname = foo\n\tMODULE[bar] { key = 1 }\n}\nPART[other] {}\n";
+        let (doc, _errors) = crate::parser::parse(input);
+        let node = doc.node_at_path("PART/MODULE[bar]").unwrap();
+        assert_eq!(*node.identifier, "MODULE");
+        assert!(doc.node_at_path("PART[foo]/MODULE").is_some());
+        assert!(doc.node_at_path("PART[other]").is_some());
+        assert!(doc.node_at_path("PART[other]/MODULE").is_none());
+        assert!(doc.node_at_path("PART/../PART").is_none());
+        assert!(doc.node_at_path("").is_none());
+    }
+
+    #[test]
+    fn test_stats() {
+        let input = "// header\n\nnode // id\n{\n\tkey = val // kv\n\n\tchild\n\t{\n\t\t// inside\n\t\tkey = val\n\t\tgarbage\n\t}\n} // trailing\ngarbage\n";
+        let (doc, errors) = crate::parser::parse(input);
+        assert_eq!(errors.len(), 2, "{errors:#?}");
+        assert_eq!(
+            doc.stats(),
+            DocStats {
+                nodes: 2,
+                keyvals: 2,
+                comments: 5,
+                empty_lines: 2,
+                errors: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_after_error() {
+        // Only the line ending of the error is skipped, not the empty line after it
+        let input = "garbage
+
+node
+{
+	garbage
+
+}
+";
+        let (doc, errors) = crate::parser::parse(input);
+        assert_eq!(errors.len(), 2, "{errors:#?}");
+        let stats = doc.stats();
+        assert_eq!((stats.empty_lines, stats.errors), (2, 2));
+    }
+
+    #[test]
+    fn test_to_sexp() {
+        let input = "// header\n@PART[foo|bar]:HAS[#mod]:FOR[Mod]\n{\n\t@mass *= 2\n\n\tMODULE\n\t{\n\t\tname = \"quoted\"\n\t}\n}\ngarbage\n";
+        let (doc, _errors) = crate::parser::parse(input);
+        assert_eq!(
+            doc.to_sexp(),
+            r#"(document (comment "// header") (node "@PART" [foo|bar] :HAS[#mod] :FOR[Mod] (keyval "@mass" "*=" "2") (empty_line) (node "MODULE" (keyval "name" "=" "\"quoted\""))) (error "garbage") (empty_line))"#
+        );
+    }
+
+    #[test]
+    fn test_top_level_ranges() {
+        let (doc, errors) = crate::parser::parse(
+            "// comment\n@PART[foo]:FOR[bar]\n{\n\tMODULE {}\n}\n\n!RESOURCE_DEFINITION[baz] {}\n",
+        );
+        assert!(errors.is_empty(), "{errors:#?}");
+        assert_eq!(
+            doc.top_level_ranges(),
+            vec![
+                ("@PART[foo]:FOR[bar]".to_owned(), Range::new(2, 1, 5, 2)),
+                (
+                    "!RESOURCE_DEFINITION[baz]".to_owned(),
+                    Range::new(7, 1, 7, 29)
+                ),
+            ]
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use super::{
+    codes,
     parser_helpers::{debug_fn, expect, non_empty, range_wrap},
     Ranged, {ASTParse, IResult, LocatedSpan},
 };
@@ -9,13 +10,13 @@ use nom::{
     character::complete::{anychar, char, line_ending},
     combinator::{map, opt, peek, recognize, value},
     multi::{many1, many_till, separated_list1},
-    sequence::{delimited, tuple},
+    sequence::{delimited, pair, tuple},
 };
 use nom_unicode::complete::alphanumeric1;
 use std::fmt::Display;
 
 /// Predicate to filter nodes for which to run an operation
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HasPredicate<'a> {
     /// Enum variant for a predicate relating to a node
     NodePredicate {
@@ -78,7 +79,7 @@ impl<'a> Display for HasPredicate<'a> {
 }
 
 /// Enum for the type of comparison to perform on a value
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum MatchType {
     /// match the value literally
     #[default]
@@ -100,7 +101,7 @@ impl Display for MatchType {
 }
 
 /// Contains a `Vec` of all the predicates to be combined using logical ANDs. All predicates have to be satisfied for the node to be a match
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct HasBlock<'a> {
     /// The predicates that are combined with logical ANDs
     pub predicates: Vec<Ranged<HasPredicate<'a>>>,
@@ -116,19 +117,20 @@ impl<'a> Display for HasBlock<'a> {
 }
 
 impl<'a> ASTParse<'a> for HasBlock<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<HasBlock<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<HasBlock<'a>>> {
         range_wrap(map(
             delimited(
                 tag_no_case(":HAS["),
                 debug_fn(
                     expect(
                         separated_list1(alt((char('&'), char(','))), HasPredicate::parse),
+                        codes::EXPECTED_TOKEN,
                         "Expected has predicate",
                     ),
                     "Got has predicates",
                     true,
                 ),
-                expect(char(']'), "Expected closing `]`"),
+                expect(char(']'), codes::UNCLOSED_BRACKET, "Expected closing `]`"),
             ),
             |inner| HasBlock {
                 predicates: inner.unwrap_or_default(),
@@ -138,17 +140,28 @@ impl<'a> ASTParse<'a> for HasBlock<'a> {
 }
 
 impl<'a> ASTParse<'a> for HasPredicate<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<HasPredicate<'a>>> {
-        let has_value = range_wrap(delimited(
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<HasPredicate<'a>>> {
+        let match_type = map(
+            opt(alt((
+                value(MatchType::GreaterThan, char('>')),
+                value(MatchType::LessThan, char('<')),
+            ))),
+            Option::unwrap_or_default,
+        );
+        let has_value = delimited(
             char('['),
-            opt(non_empty(recognize(many_till(
-                anychar,
-                peek(alt((line_ending::<LocatedSpan, _>, tag("]"), tag("//")))),
-            )))),
-            expect(char(']'), "Expected closing `]`"),
-        ));
+            pair(
+                match_type,
+                range_wrap(opt(non_empty(recognize(many_till(
+                    anychar,
+                    peek(alt((line_ending::<LocatedSpan, _>, tag("]"), tag("//")))),
+                ))))),
+            ),
+            expect(char(']'), codes::UNCLOSED_BRACKET, "Expected closing `]`"),
+        );
         let value_determinative = expect(
             alt((value(false, char('#')), value(true, char('~')))),
+            codes::EXPECTED_TOKEN,
             "Expected # or ~",
         );
         let value_constraint = map(
@@ -157,16 +170,15 @@ impl<'a> ASTParse<'a> for HasPredicate<'a> {
                 identifier,
                 debug_fn(opt(has_value), "Got value", true),
             )),
-            |inner: (
-                Option<bool>,
-                LocatedSpan,
-                Option<Ranged<Option<LocatedSpan>>>,
-            )| {
+            |inner: (Option<bool>, LocatedSpan, Option<HasValue>)| {
+                let (match_type, value) = inner.2.map_or((MatchType::Literal, None), |v| {
+                    (v.0, Some(v.1.map(|s| s.map_or("", |s| *s.fragment()))))
+                });
                 HasPredicate::KeyPredicate {
                     negated: inner.0.unwrap_or_default(),
                     key: inner.1.fragment(),
-                    value: inner.2.map(|s| s.map(|s| s.map_or("", |s| s.fragment()))),
-                    match_type: MatchType::Literal,
+                    value,
+                    match_type,
                 }
             },
         );
@@ -174,10 +186,11 @@ impl<'a> ASTParse<'a> for HasPredicate<'a> {
         let name_constraint = delimited(
             char('['),
             recognize(many1(alt((alphanumeric1, is_a("/_-?*.|"))))),
-            expect(char(']'), "Expected closing `]`"),
+            expect(char(']'), codes::UNCLOSED_BRACKET, "Expected closing `]`"),
         );
         let node_determinative = expect(
             alt((value(false, char('@')), value(true, char('!')))),
+            codes::EXPECTED_TOKEN,
             "Expected @ or !",
         );
         let node_constraint = map(
@@ -199,6 +212,9 @@ impl<'a> ASTParse<'a> for HasPredicate<'a> {
     }
 }
 
+/// The match type and value of a key predicate, `[>value]`
+type HasValue<'a> = (MatchType, Ranged<Option<LocatedSpan<'a>>>);
+
 fn identifier(input: LocatedSpan) -> IResult<LocatedSpan> {
     recognize(many1(alt((alphanumeric1, is_a("-_.+*?")))))(input)
 }
@@ -218,4 +234,28 @@ mod tests {
             Err(err) => panic!("{}", err),
         }
     }
+
+    #[test]
+    fn test_match_type() {
+        for (input, expected, value) in [
+            (":HAS[#mass[>2.0]]", MatchType::GreaterThan, "2.0"),
+            (":HAS[~mass[<2]]", MatchType::LessThan, "2"),
+            (":HAS[#mass[2]]", MatchType::Literal, "2"),
+        ] {
+            let (rest, has) =
+                HasBlock::parse(LocatedSpan::new_extra(input, State::default())).unwrap();
+            assert!(rest.extra.errors.borrow().is_empty());
+            assert_eq!(input, has.to_string());
+            let HasPredicate::KeyPredicate {
+                match_type,
+                value: Some(parsed_value),
+                ..
+            } = has.predicates[0].as_ref()
+            else {
+                panic!("expected a key predicate in {input}")
+            };
+            assert_eq!(*match_type, expected);
+            assert_eq!(**parsed_value, value);
+        }
+    }
 }
@@ -1,22 +1,26 @@
 use super::{
+    codes,
     parser_helpers::{expect, range_wrap},
     Ranged, {ASTParse, IResult, LocatedSpan},
 };
 use nom::{
     branch::alt,
-    character::complete::{char, digit1, none_of},
-    combinator::{map, map_res, opt, value},
-    sequence::{delimited, pair, preceded},
+    bytes::complete::take_till,
+    character::complete::{char, digit1, none_of, satisfy},
+    combinator::{eof, map, map_res, opt, peek, recognize, value},
+    sequence::{delimited, pair, preceded, terminated},
 };
 use std::fmt::Display;
 
 /// Selects from multiple matching objects
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Index {
     /// Operate on all matches, `,*`
     All,
     /// Integer match to operate on. Can be negative to start from back, `,i`
     Number(i32),
+    /// Anything else following the `,`, which is not a valid index. Contains the raw text after the `,`
+    Invalid(String),
 }
 
 impl Display for Index {
@@ -24,6 +28,7 @@ impl Display for Index {
         match self {
             Index::All => write!(f, ",*"),
             Index::Number(n) => write!(f, ",{n}"),
+            Index::Invalid(raw) => write!(f, ",{raw}"),
         }
     }
 }
@@ -31,25 +36,34 @@ impl Display for Index {
 impl ASTParse<'_> for Index {
     fn parse(input: LocatedSpan) -> IResult<Ranged<Index>> {
         // index = { "," ~ ("*" | ("-"? ~ ASCII_DIGIT+)) }
+        // Anything else up to the next part of the identifier is kept as an invalid index, to be reported by the linter
         range_wrap(preceded(
             char(','),
             alt((
-                value(Index::All, char('*')),
-                map(preceded(opt(char('-')), digit1), |inner: LocatedSpan| {
-                    Index::Number(
-                        inner
-                            .fragment()
-                            .parse()
-                            .expect("Only digits are allowed to get through the parser"),
-                    )
+                value(Index::All, terminated(char('*'), peek(index_end))),
+                map_res(
+                    terminated(recognize(pair(opt(char('-')), digit1)), peek(index_end)),
+                    |inner: LocatedSpan| inner.fragment().parse().map(Index::Number),
+                ),
+                map(take_till(is_index_terminator), |inner: LocatedSpan| {
+                    Index::Invalid((*inner.fragment()).to_owned())
                 }),
             )),
         ))(input)
     }
 }
 
+/// Characters that can follow an index
+fn is_index_terminator(c: char) -> bool {
+    c.is_whitespace() || ":[]{}=,/".contains(c)
+}
+
+fn index_end(input: LocatedSpan) -> IResult<()> {
+    alt((value((), eof), value((), satisfy(is_index_terminator))))(input)
+}
+
 /// index in value of variable to operate on
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ArrayIndex {
     /// Index to operate on, all if `None` (from `*`)
     pub index: Option<i32>,
@@ -78,18 +92,23 @@ impl ASTParse<'_> for ArrayIndex {
                     value(None, char('*')),
                     map_res(digit1, |n: LocatedSpan| n.fragment().parse().map(Some)),
                 )),
+                codes::EXPECTED_TOKEN,
                 "Expected index, or *",
             ),
             opt(preceded(
                 char(','),
-                expect(none_of("]"), "Expected char between `,` and closing `]`"),
+                expect(
+                    none_of("]"),
+                    codes::EXPECTED_TOKEN,
+                    "Expected char between `,` and closing `]`",
+                ),
             )),
         );
         range_wrap(map(
             delimited(
                 char('['),
                 array_index,
-                expect(char(']'), "Expected closing `]`"),
+                expect(char(']'), codes::UNCLOSED_BRACKET, "Expected closing `]`"),
             ),
             |inner| ArrayIndex {
                 index: inner.0.unwrap_or_default(),
@@ -98,3 +117,27 @@ impl ASTParse<'_> for ArrayIndex {
         ))(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{LocatedSpan, State};
+
+    use super::*;
+
+    #[test]
+    fn test_index() {
+        for (input, expected) in [
+            (",*", Index::All),
+            (",3", Index::Number(3)),
+            (",-1", Index::Number(-1)),
+            (",", Index::Invalid(String::new())),
+            (",1a", Index::Invalid("1a".to_owned())),
+        ] {
+            let (rest, index) =
+                Index::parse(LocatedSpan::new_extra(input, State::default())).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(index.inner, expected);
+            assert_eq!(index.to_string(), input);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use super::{
-    parser_helpers::{debug_fn, ignore_line_ending, range_wrap, ws},
-    ASTPrint, ArrayIndex, AssignmentOperator, Comment, Index, NeedsBlock, Operator, Path, Range,
-    Ranged, {ASTParse, IResult, LocatedSpan},
+    parser_helpers::{debug_fn, ignore_line_ending, printed_eq, range_wrap},
+    ASTPrint, ArrayIndex, AssignmentOperator, Comment, Index, NeedsBlock, Operator, Path,
+    PrintSettings, Range, Ranged, {ASTParse, IResult, LocatedSpan},
 };
 use nom::{
     branch::alt,
@@ -10,11 +10,13 @@ use nom::{
     combinator::{all_consuming, eof, map, opt, peek, recognize},
     multi::{many1, many_till, separated_list1},
     sequence::{pair, preceded, terminated, tuple},
+    InputTake,
 };
 use nom_unicode::complete::alphanumeric1;
+use std::borrow::Cow;
 
 /// Assignment operation
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct KeyVal<'a> {
     /// Optional path to the variable
     pub path: Option<Ranged<Path<'a>>>,
@@ -28,16 +30,62 @@ pub struct KeyVal<'a> {
     pub index: Option<Ranged<Index>>,
     /// Optional array-index
     pub array_index: Option<Ranged<ArrayIndex>>,
-    key_padding: Option<usize>,
+    key_padding: KeyPadding,
+    operator_whitespace: &'a str,
     /// The assignment operator between the variable and the value
     pub assignment_operator: Ranged<AssignmentOperator>,
-    /// The value to use in the assignment
-    pub val: Ranged<&'a str>,
+    /// The value to use in the assignment. Borrowed from the parsed text, unless replaced by a transformer
+    pub val: Ranged<Cow<'a, str>>,
     /// Optional trailing comment
     pub comment: Option<Ranged<Comment<'a>>>,
 }
 
+/// How the space before the assignment operator is printed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum KeyPadding {
+    /// A single space
+    #[default]
+    Single,
+    /// This many spaces, in addition to the single space
+    Spaces(usize),
+    /// The whitespace before the operator, as written
+    AsWritten,
+}
+
 impl<'a> KeyVal<'a> {
+    /// Prints the assignment on its own, without needing to import [`ASTPrint`]. `collapse` is used like the `inline` setting of [`crate::Formatter::new()`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::{parse, DocItem};
+    ///
+    /// let (doc, _errors) = parse("node { key   =   val }");
+    /// let DocItem::Node(node) = &doc.statements[0] else { panic!() };
+    /// let key_val = node.iter_keyvals().next().unwrap();
+    /// assert_eq!(key_val.to_string_with("\t", "\n", None), "key = val\n");
+    /// ```
+    #[must_use]
+    pub fn to_string_with(
+        &self,
+        indentation: &str,
+        line_ending: &str,
+        collapse: Option<bool>,
+    ) -> String {
+        self.ast_print(0, indentation, line_ending, &PrintSettings::new(collapse))
+    }
+    /// Compares two assignments, ignoring the ranges of all items and any print settings set by a transformer
+    #[must_use]
+    pub fn eq_ignore_ranges(&self, other: &Self) -> bool {
+        printed_eq(&self.path, &other.path)
+            && self.operator.as_deref() == other.operator.as_deref()
+            && *self.key == *other.key
+            && printed_eq(&self.needs, &other.needs)
+            && self.index.as_deref() == other.index.as_deref()
+            && self.array_index.as_deref() == other.array_index.as_deref()
+            && *self.assignment_operator == *other.assignment_operator
+            && *self.val == *other.val
+            && self.comment.as_deref() == other.comment.as_deref()
+    }
     pub(crate) fn left_side(&self) -> String {
         format!(
             "{}{}{}{}{}{}{}",
@@ -57,7 +105,15 @@ impl<'a> KeyVal<'a> {
         )
     }
     pub(crate) fn set_key_padding(&mut self, n: usize) {
-        self.key_padding = Some(n - self.left_side().len());
+        self.key_padding = KeyPadding::Spaces(n.saturating_sub(self.left_side().chars().count()));
+    }
+    /// The whitespace between the left side and the assignment operator, as written
+    pub(crate) const fn operator_whitespace(&self) -> &'a str {
+        self.operator_whitespace
+    }
+    /// Prints the whitespace before the assignment operator as written, instead of a single space
+    pub(crate) fn keep_operator_whitespace(&mut self) {
+        self.key_padding = KeyPadding::AsWritten;
     }
 }
 
@@ -67,11 +123,11 @@ impl<'a> ASTPrint for KeyVal<'a> {
         depth: usize,
         indentation: &str,
         line_ending: &str,
-        _: Option<bool>,
+        settings: &PrintSettings,
     ) -> String {
         let indentation = indentation.repeat(depth);
         format!(
-            "{}{}{}{}{}{}{}{}{} {} {}{}{}",
+            "{}{}{}{}{}{}{}{}{}{} {}{}{}",
             indentation,
             if self.path.is_some() { "*" } else { "" },
             self.path
@@ -86,17 +142,24 @@ impl<'a> ASTPrint for KeyVal<'a> {
             self.array_index
                 .as_deref()
                 .map_or_else(String::new, std::string::ToString::to_string),
-            self.key_padding.map_or_else(String::new, |p| " ".repeat(p)),
+            match self.key_padding {
+                KeyPadding::Single => " ".to_owned(),
+                KeyPadding::Spaces(n) => " ".repeat(n + 1),
+                KeyPadding::AsWritten => self.operator_whitespace.to_owned(),
+            },
             self.assignment_operator,
-            self.val,
-            self.comment.as_ref().map_or("", |c| c.text),
+            *self.val,
+            self.comment
+                .as_ref()
+                .map(|c| c.print_text(settings))
+                .unwrap_or_default(),
             line_ending
         )
     }
 }
 
 impl<'a> ASTParse<'a> for KeyVal<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<KeyVal<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<KeyVal<'a>>> {
         let parser = move |input| {
             // This parses anything that could potentially be a key
             let (input, dumb_key) = recognize(many_till(
@@ -109,23 +172,18 @@ impl<'a> ASTParse<'a> for KeyVal<'a> {
             ))(input)?;
             let (complete_key, errors) = proper_key_parser(dumb_key);
 
-            let (input, assignment_operator) = ws(AssignmentOperator::parse)(input)?;
+            let (input, (operator_whitespace, assignment_operator)) =
+                terminated(pair(space0, AssignmentOperator::parse), space0)(input)?;
 
             let (input, (value, comment)) = map(
                 ignore_line_ending(pair(
-                    range_wrap(recognize(many_till(
-                        anychar,
-                        peek(alt((
-                            recognize(Comment::parse),
-                            preceded(space0, is_a("}\r\n")),
-                        ))),
-                    ))),
+                    range_wrap(value_text),
                     terminated(
                         opt(Comment::parse),
                         opt(terminated(space0, peek(line_ending))),
                     ),
                 )),
-                |(s, c)| (s.map(|s| *s.fragment()), c),
+                |(s, c)| (s.map(|s| Cow::Borrowed(*s.fragment())), c),
             )(input)?;
 
             // let (input, comment) = opt(ignore_line_ending(Comment::parse))(input)?;
@@ -137,7 +195,8 @@ impl<'a> ASTParse<'a> for KeyVal<'a> {
                 needs: complete_key.3,
                 index: complete_key.4,
                 array_index: complete_key.5,
-                key_padding: None,
+                key_padding: KeyPadding::Single,
+                operator_whitespace: operator_whitespace.fragment(),
                 assignment_operator,
                 val: value,
                 comment,
@@ -151,6 +210,19 @@ impl<'a> ASTParse<'a> for KeyVal<'a> {
     }
 }
 
+/// The text of the value, ending before a comment, `}`, or the end of the line or input. Whitespace before the end is not included
+fn value_text(input: LocatedSpan<'_>) -> IResult<'_, LocatedSpan<'_>> {
+    let fragment = input.fragment();
+    // Find the end in a single pass, since values can be very long
+    let end = fragment
+        .char_indices()
+        .find(|&(i, c)| matches!(c, '}' | '\r' | '\n') || fragment[i..].starts_with("//"))
+        .map_or(fragment.len(), |(i, _)| i);
+    let len = fragment[..end].trim_end_matches([' ', '\t']).len();
+    // `len` is in bytes, while `take` counts chars
+    Ok(input.take_split(len))
+}
+
 type ParsedKey<'a> = (
     Option<Ranged<Path<'a>>>,
     Option<Ranged<Operator>>,
@@ -160,7 +232,7 @@ type ParsedKey<'a> = (
     Option<Ranged<ArrayIndex>>,
 );
 
-fn proper_key_parser(input: LocatedSpan<'_>) -> (ParsedKey, Vec<super::Error>) {
+fn proper_key_parser(input: LocatedSpan<'_>) -> (ParsedKey<'_>, Vec<super::Error>) {
     // Clear errors on dumb_key to avoid duplicated errors
     input.extra.errors.borrow_mut().clear();
 
@@ -215,6 +287,7 @@ fn proper_key_parser(input: LocatedSpan<'_>) -> (ParsedKey, Vec<super::Error>) {
             (
                 res_tuple,
                 vec![super::Error {
+                    code: super::codes::BAD_KEY,
                     message: format!(
                         "failed to parse key. Unexpected `{}`",
                         error.input.fragment()
@@ -242,17 +315,63 @@ mod tests {
         let res = KeyVal::parse(LocatedSpan::new_extra(input, State::default()));
 
         match res {
-            Ok(it) => assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", None)),
+            Ok(it) => assert_eq!(
+                input,
+                it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(None))
+            ),
             Err(err) => panic!("{}", err),
         }
     }
     #[test]
+    fn test_key_index() {
+        for (input, index) in [
+            ("@key,0 = v\n", Index::Number(0)),
+            ("@key,* = v\n", Index::All),
+            ("@key,-1 = v\n", Index::Number(-1)),
+        ] {
+            let (rest, key_val) =
+                KeyVal::parse(LocatedSpan::new_extra(input, State::default())).unwrap();
+            assert!(rest.is_empty());
+            assert!(rest.extra.errors.borrow().is_empty(), "{input}");
+            assert_eq!(key_val.index.as_deref(), Some(&index));
+            assert_eq!(
+                key_val.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+                input
+            );
+        }
+    }
+    #[test]
+    fn test_numeric_keys() {
+        for (input, key) in [
+            ("0 = v\n", "0"),
+            ("12 = v\n", "12"),
+            ("1key = v\n", "1key"),
+            ("@1key,0 = v\n", "1key"),
+        ] {
+            let (_, key_val) = KeyVal::parse(LocatedSpan::new_extra(input, State::default()))
+                .unwrap_or_else(|err| panic!("{input:?}: {err}"));
+            assert_eq!(*key_val.key, key);
+            assert_eq!(
+                input,
+                key_val.ast_print(0, "\t", "\n", &PrintSettings::new(None))
+            );
+        }
+        // Numeric keys are not mistaken for MM syntax by the linter
+        let input = "PART\n{\n\t0 = v\n\t1key = v\n}\n";
+        let (_doc, errors, diagnostics) = crate::parse_partial(input);
+        assert!(errors.is_empty(), "{errors:#?}");
+        assert!(diagnostics.is_empty(), "{diagnostics:#?}");
+    }
+    #[test]
     fn test_key_val_2() {
         let input = "*@PART[RO-M55]/deleteMe = true\r\n";
         let res = KeyVal::parse(LocatedSpan::new_extra(input, State::default()));
 
         match res {
-            Ok(it) => assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", None)),
+            Ok(it) => assert_eq!(
+                input,
+                it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(None))
+            ),
             Err(err) => panic!("{}", err),
         }
     }
@@ -268,9 +387,52 @@ mod tests {
                     it.0.extra.errors.into_inner()[0].message,
                     "failed to parse key. Unexpected `-1]`"
                 );
-                assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", None))
+                assert_eq!(
+                    input,
+                    it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(None))
+                )
             }
             Err(err) => panic!("{}", err),
         }
     }
+
+    #[test]
+    fn test_value_end() {
+        for (input, value, comment) in [
+            ("key = a b\n", "a b", None),
+            ("key = a b \t\r\n", "a b", None),
+            ("key = a/b // c\n", "a/b", Some(" // c")),
+            ("key = a//c\n", "a", Some("//c")),
+            ("key = a b }", "a b", None),
+            ("key = \n", "", None),
+            ("key = aéb\n", "aéb", None),
+            ("key = a\u{FFFD}b }", "a\u{FFFD}b", None),
+        ] {
+            let (_, key_val) = KeyVal::parse(LocatedSpan::new_extra(input, State::default()))
+                .unwrap_or_else(|err| panic!("{input:?}: {err}"));
+            assert_eq!(*key_val.val, value, "{input:?}");
+            assert_eq!(
+                key_val.comment.as_ref().map(|c| c.text),
+                comment,
+                "{input:?}"
+            );
+        }
+        // The value can end at the end of the input
+        let (_, key_val) =
+            KeyVal::parse(LocatedSpan::new_extra("key = val", State::default())).unwrap();
+        assert_eq!(*key_val.val, "val");
+    }
+
+    #[test]
+    fn test_long_value() {
+        // Multi-byte characters count once when finding the end
+        let value = "wörd  ".repeat(10_000) + "end";
+        let input = format!("node\n{{\n\tkey = {value} // comment\n}}\n");
+        let (doc, errors) = crate::parser::parse(&input);
+        assert!(errors.is_empty(), "{errors:#?}");
+        assert_eq!(
+            doc.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+            input
+        );
+    }
 }
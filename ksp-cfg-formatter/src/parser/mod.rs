@@ -17,10 +17,12 @@ mod operator;
 mod parser_helpers;
 mod pass;
 mod path;
+mod reparse;
 
 pub use assignment_operator::AssignmentOperator;
+pub(crate) use comment::block_comment;
 pub use comment::Comment;
-pub use document::{DocItem, Document};
+pub use document::{DocItem, DocStats, Document};
 pub use has::{HasBlock, HasPredicate, MatchType};
 pub use indices::{ArrayIndex, Index};
 pub use key_val::KeyVal;
@@ -30,12 +32,139 @@ pub use node_item::NodeItem;
 pub use operator::Operator;
 pub use pass::Pass;
 pub use path::{Path, PathSegment, PathStart};
+pub use reparse::reparse_node_at;
 
 /// This used in place of `&str` or `&[u8]` in our `nom` parsers.
 pub(crate) type LocatedSpan<'a> = nom_locate::LocatedSpan<&'a str, State>;
 /// Convenient type alias for `nom::IResult<I, O>` reduced to `IResult<O>`.
 pub(crate) type IResult<'a, T> = nom::IResult<LocatedSpan<'a>, T>;
 
+/// Where the opening bracket of a node spanning multiple lines is placed
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{BraceStyle, Formatter, Indentation, LineReturn};
+///
+/// let text = "node\n{\n\tkey = val\n}\n";
+/// let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+/// assert_eq!(formatter.brace_style(BraceStyle::OwnLine).format_text(text).unwrap(), text);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BraceStyle {
+    /// The opening bracket is placed on its own line, below the node identifier
+    #[default]
+    OwnLine,
+    /// The opening bracket is placed on the same line as the node identifier, `node {`
+    SameLine,
+}
+
+/// Settings used when printing the AST, that are not related to whitespace
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::parser::{BraceStyle, PrintSettings};
+///
+/// let settings = PrintSettings::new(Some(true)).brace_style(BraceStyle::SameLine);
+/// assert_eq!(settings.brace_style, BraceStyle::SameLine);
+/// assert_eq!(settings.should_collapse, Some(true));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintSettings {
+    /// Whether blocks with a single item should be collapsed to one line. See [`crate::Formatter::new`]
+    pub should_collapse: Option<bool>,
+    /// Where the opening bracket of a multi-line block is placed
+    pub brace_style: BraceStyle,
+    /// Whether comments are printed with exactly one space after `//`. See [`Comment::print_text`]
+    pub normalize_comments: bool,
+    /// Whether an empty line is added between top level nodes that are next to each other. See [`Document`]
+    pub blank_line_between_top_level: bool,
+    /// Whether the whitespace before comments inside nodes is kept as written, instead of the comments being indented to the depth of the block. See [`NodeItem`]
+    pub keep_comment_indentation: bool,
+    /// Whether empty blocks are printed as `node { }` instead of `node {}`
+    pub space_in_empty_braces: bool,
+    /// Lines longer than this are not collapsed. See [`crate::Formatter::max_line_length`]
+    pub max_line_length: usize,
+}
+
+impl Default for PrintSettings {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl PrintSettings {
+    /// Constructs new `PrintSettings`, with the rest of the settings at their default values
+    #[must_use]
+    pub const fn new(should_collapse: Option<bool>) -> Self {
+        Self {
+            should_collapse,
+            brace_style: BraceStyle::OwnLine,
+            normalize_comments: false,
+            blank_line_between_top_level: false,
+            keep_comment_indentation: false,
+            space_in_empty_braces: false,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+        }
+    }
+
+    /// Sets where the opening bracket of multi-line blocks is placed
+    #[must_use]
+    pub const fn brace_style(self, brace_style: BraceStyle) -> Self {
+        Self {
+            brace_style,
+            ..self
+        }
+    }
+
+    /// Sets whether comments are printed with exactly one space after `//`
+    #[must_use]
+    pub const fn normalize_comments(self, normalize_comments: bool) -> Self {
+        Self {
+            normalize_comments,
+            ..self
+        }
+    }
+
+    /// Sets whether an empty line is added between top level nodes that are next to each other
+    #[must_use]
+    pub const fn blank_line_between_top_level(self, blank_line_between_top_level: bool) -> Self {
+        Self {
+            blank_line_between_top_level,
+            ..self
+        }
+    }
+
+    /// Sets whether the whitespace before comments inside nodes is kept as written, instead of the comments being indented to the depth of the block
+    #[must_use]
+    pub const fn keep_comment_indentation(self, keep_comment_indentation: bool) -> Self {
+        Self {
+            keep_comment_indentation,
+            ..self
+        }
+    }
+
+    /// Sets whether empty blocks are printed as `node { }` instead of `node {}`
+    #[must_use]
+    pub const fn space_in_empty_braces(self, space_in_empty_braces: bool) -> Self {
+        Self {
+            space_in_empty_braces,
+            ..self
+        }
+    }
+
+    /// Sets the length of the longest line that a node can be collapsed to
+    #[must_use]
+    pub const fn max_line_length(self, max_line_length: usize) -> Self {
+        Self {
+            max_line_length,
+            ..self
+        }
+    }
+}
+
+/// The default for [`PrintSettings::max_line_length`]
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 72;
+
 /// Indicates that the type can be pretty-printed as part of the formatter
 pub trait ASTPrint {
     /// Pretty-print the type to a string, ready to be written to file/output
@@ -45,7 +174,7 @@ pub trait ASTPrint {
         depth: usize,
         indentation: &str,
         line_ending: &str,
-        should_collapse: Option<bool>,
+        settings: &PrintSettings,
     ) -> String;
 }
 
@@ -54,7 +183,7 @@ pub trait ASTParse<'c> {
     /// Parse the type the trait is implemented for, from the input
     /// # Errors
     /// Returns an error if the parser fails
-    fn parse(input: LocatedSpan<'c>) -> IResult<Ranged<Self>>
+    fn parse(input: LocatedSpan<'c>) -> IResult<'c, Ranged<Self>>
     where
         Self: Sized;
 }
@@ -62,8 +191,24 @@ pub trait ASTParse<'c> {
 /// Parses a string into a document struct, also emmitting errors along the way
 /// # Panics
 /// The parser is designed to never fail. If it panics, there is a bug in the parser
-pub fn parse(source: &str) -> (Document, Vec<Error>) {
-    let input = LocatedSpan::new_extra(source, State::default());
+pub fn parse(source: &str) -> (Document<'_>, Vec<Error>) {
+    parse_with_max_depth(source, ParserState::DEFAULT_MAX_DEPTH)
+}
+
+/// Same as [`parse`], but with a custom maximum nesting depth of nodes.
+///
+/// Nodes nested deeper than `max_depth` are reported as errors instead of being parsed
+/// # Panics
+/// The parser is designed to never fail. If it panics, there is a bug in the parser
+pub fn parse_with_max_depth(source: &str, max_depth: usize) -> (Document<'_>, Vec<Error>) {
+    let state = State {
+        state: ParserState {
+            max_depth,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let input = LocatedSpan::new_extra(source, state);
     let Ok((span, doc)) = nom::combinator::all_consuming(document::source_file)(input) else {
         panic!("The parser is designed to not be able to fail, but it did. Please report this as a bug!");
     };
@@ -72,6 +217,74 @@ pub fn parse(source: &str) -> (Document, Vec<Error>) {
     (doc.inner, errors)
 }
 
+/// Parses a single assignment, like `key = val`. Trailing whitespace and line endings are allowed
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::parser::parse_key_val;
+///
+/// let key_val = parse_key_val("@key,0 = val // comment\n").unwrap();
+/// assert_eq!(*key_val.key, "key");
+/// assert_eq!(*key_val.val, "val");
+/// ```
+/// # Errors
+/// Returns the errors found while parsing, or if `source` contains anything other than one assignment
+pub fn parse_key_val(source: &str) -> Result<Ranged<KeyVal<'_>>, Vec<Error>> {
+    parse_complete(source.trim_end(), "assignment")
+}
+
+/// Parses a single node, like `PART { key = val }`. Trailing whitespace and line endings are allowed
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::parser::parse_node;
+///
+/// let node = parse_node("@PART[foo]\n{\n\tkey = val\n}\n").unwrap();
+/// assert_eq!(*node.identifier, "PART");
+/// assert_eq!(node.block.len(), 1);
+/// ```
+/// # Errors
+/// Returns the errors found while parsing, or if `source` contains anything other than one node
+pub fn parse_node(source: &str) -> Result<Ranged<Node<'_>>, Vec<Error>> {
+    parse_complete(source.trim_end(), "node")
+}
+
+/// Parses the whole `source` as a `T`, where `expected` describes what `T` is in the error message.
+///
+/// Fails if the parser fails, reports any errors, or if there is text left after parsing
+pub(crate) fn parse_complete<'a, T: ASTParse<'a>>(
+    source: &'a str,
+    expected: &str,
+) -> Result<Ranged<T>, Vec<Error>> {
+    let input = LocatedSpan::new_extra(source, State::default());
+    let Ok((rest, parsed)) = T::parse(input.clone()) else {
+        return Err(vec![Error {
+            severity: Severity::Error,
+            code: codes::EXPECTED_TOKEN,
+            range: Range::from(input),
+            source: source.to_string(),
+            message: format!("expected {expected}"),
+            context: None,
+        }]);
+    };
+    let mut errors = rest.extra.errors.borrow().clone();
+    if !rest.is_empty() {
+        errors.push(Error {
+            severity: Severity::Error,
+            code: codes::UNEXPECTED_TEXT,
+            range: Range::from(rest.clone()),
+            source: (*rest.fragment()).to_string(),
+            message: format!("unexpected `{}` after {expected}", rest.fragment()),
+            context: None,
+        });
+    }
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(errors)
+    }
+}
+
 /// Carried around in the `LocatedSpan::extra` field in
 /// between `nom` parsers.
 #[derive(Clone, Debug)]
@@ -100,23 +313,62 @@ impl State {
 }
 
 /// Holds the state of the parser, to allow for context aware parsing
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct ParserState {
     /// Indicates if the current node is on the top level
     pub top_level: bool,
+    /// How many nodes deep the parser currently is
+    pub depth: usize,
+    /// Nodes nested deeper than this are not parsed, and reported as an error instead
+    pub max_depth: usize,
+}
+
+impl ParserState {
+    /// The default maximum nesting depth of nodes
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
 }
 
 impl Default for ParserState {
     fn default() -> Self {
-        Self { top_level: true }
+        Self {
+            top_level: true,
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
     }
 }
 
+/// Ids of the different kinds of parser errors, used as [`Error::code`]
+pub mod codes {
+    /// A `]` or `}` is missing
+    pub const UNCLOSED_BRACKET: &str = "unclosed_bracket";
+    /// A required part of the syntax is missing, like the name of a pass
+    pub const EXPECTED_TOKEN: &str = "expected_token";
+    /// Text that does not belong where it was found
+    pub const UNEXPECTED_TEXT: &str = "unexpected_text";
+    /// The identifier of a node could not be parsed
+    pub const BAD_IDENTIFIER: &str = "bad_identifier";
+    /// The key of an assignment could not be parsed
+    pub const BAD_KEY: &str = "bad_key";
+    /// A node has more than one HAS, NEEDS or pass block
+    pub const DUPLICATE_BLOCK: &str = "duplicate_block";
+    /// A mod name in a NEEDS block contains spaces
+    pub const BAD_MOD_NAME: &str = "bad_mod_name";
+    /// A mod in a NEEDS block has a version, like `mod:1.2`
+    pub const NEEDS_VERSION: &str = "needs_version";
+    /// A `/* */` block comment
+    pub const BLOCK_COMMENT: &str = "block_comment";
+    /// Nodes are nested deeper than the maximum depth
+    pub const NESTED_TOO_DEEP: &str = "nested_too_deep";
+}
+
 /// Error containing a text span and an error message to display.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Error {
     /// The severity of the error
     pub severity: Severity,
+    /// Identifies the kind of error, using the ids in [`codes`]. Unlike the message, the code is kept stable
+    pub code: &'static str,
     /// The Range covered by the error
     pub range: Range,
     /// The source string producing the error
@@ -128,7 +380,9 @@ pub struct Error {
 }
 
 /// Represents the severity of the error
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// Ordered from most to least severe, so `Severity::Error < Severity::Warning`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Severity {
     /// This issue will make the cfg not work
     Error,
@@ -140,6 +394,17 @@ pub enum Severity {
     Hint,
 }
 
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+            Self::Info => write!(f, "info"),
+            Self::Hint => write!(f, "hint"),
+        }
+    }
+}
+
 /// Wrapper to hold the range that the inner type spans
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Ranged<T> {
@@ -180,6 +445,18 @@ impl<T> Ranged<T> {
             range: self.range,
         }
     }
+
+    /// Consumes the wrapper, returning the inner item
+    #[must_use]
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Consumes the wrapper, returning the inner item and the range it spans
+    #[must_use]
+    pub fn into_parts(self) -> (T, Range) {
+        (self.inner, self.range)
+    }
 }
 
 impl<T> AsRef<T> for Ranged<T> {
@@ -263,6 +540,37 @@ impl Range {
         }
     }
 
+    /// Creates a range from byte offsets into `text`, e.g. from a regex match on the source. Offsets past the end of `text` are placed at the end of the file,
+    /// and offsets inside a multi-byte char are moved back to the start of the char
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::Range;
+    ///
+    /// let text = "node\n{\n\tkey = val\n}\n";
+    /// let start = text.find("key").unwrap();
+    /// assert_eq!(Range::from_offsets(text, start, start + 3), Range::new(3, 2, 3, 5));
+    /// ```
+    #[must_use]
+    pub fn from_offsets(text: &str, start: usize, end: usize) -> Self {
+        let position = |offset: usize| {
+            let mut offset = offset.min(text.len());
+            while !text.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            let before = &text[..offset];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            Position::new(
+                before.matches('\n').count() as u32 + 1,
+                before[line_start..].chars().count() as u32 + 1,
+            )
+        };
+        Self {
+            start: position(start),
+            end: position(end),
+        }
+    }
+
     /// Creates a Range with the end set to the same as the start of the current range
     #[must_use]
     pub const fn to_start(&self) -> Self {
@@ -281,9 +589,44 @@ impl Range {
         }
     }
 
+    /// Number of line breaks the range spans. A range on a single line has a line span of 0
+    #[must_use]
+    pub const fn line_span(&self) -> u32 {
+        self.end.line.saturating_sub(self.start.line)
+    }
+
+    /// Indicates if the range starts and ends on the same line
+    #[must_use]
+    pub const fn is_single_line(&self) -> bool {
+        self.line_span() == 0
+    }
+
+    /// Indicates if the range is empty, with the start and end at the same position
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.start.line == self.end.line && self.start.col == self.end.col
+    }
+
+    /// Combines overlapping ranges into one range, creating a sorted set of non-overlapping ranges as output
+    ///
+    /// Adjacent ranges, where one ends where the next starts, are also combined. See [`Range::combine_ranges_strict`] to keep them separate
+    #[must_use]
+    pub fn combine_ranges(ranges: Vec<Range>) -> Vec<Range> {
+        Self::combine_ranges_if(ranges, |curr_range, range| range.start <= curr_range.end)
+    }
+
     /// Combines overlapping ranges into one range, creating a sorted set of non-overlapping ranges as output
+    ///
+    /// Unlike [`Range::combine_ranges`], adjacent ranges that only touch are kept separate
     #[must_use]
-    pub fn combine_ranges(mut ranges: Vec<Range>) -> Vec<Range> {
+    pub fn combine_ranges_strict(ranges: Vec<Range>) -> Vec<Range> {
+        Self::combine_ranges_if(ranges, |curr_range, range| range.start < curr_range.end)
+    }
+
+    fn combine_ranges_if(
+        mut ranges: Vec<Range>,
+        should_combine: impl Fn(&Range, &Range) -> bool,
+    ) -> Vec<Range> {
         if ranges.is_empty() {
             return vec![];
         }
@@ -291,7 +634,7 @@ impl Range {
         let mut ret_ranges = vec![];
         let mut curr_range = ranges[0];
         for range in ranges.into_iter().skip(1) {
-            if range.start <= curr_range.end {
+            if should_combine(&curr_range, &range) {
                 curr_range = curr_range + range;
                 continue;
             }
@@ -315,23 +658,122 @@ impl std::ops::Add for Range {
 
 impl Display for Range {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.end.line - self.start.line > 0 {
+        if self.is_single_line() {
+            write!(f, "{}:{}-{}", self.start.line, self.start.col, self.end.col)
+        } else {
             write!(
                 f,
                 "[{}, {}] to [{}, {}]",
                 self.start.line, self.start.col, self.end.line, self.end.col
             )
-        } else {
-            write!(f, "{}:{}-{}", self.start.line, self.start.col, self.end.col)
         }
     }
 }
 
+// The parser counts lines and columns from 1, while LSP counts them from 0, so every conversion adds or removes 1.
+/// Converts the position as is, with the column counted in chars.
+///
+/// LSP counts columns in UTF-16 code units, so this is only correct when the line before the position has no characters outside the Basic Multilingual Plane. Use [`Position::to_lsp`] to convert using the source text
+#[cfg(feature = "lsp")]
+impl From<Position> for lsp_types::Position {
+    fn from(value: Position) -> Self {
+        Self::new(value.line.saturating_sub(1), value.col.saturating_sub(1))
+    }
+}
+
+/// Converts the position as is, with the column counted in chars.
+///
+/// LSP counts columns in UTF-16 code units, so this is only correct when the line before the position has no characters outside the Basic Multilingual Plane. Use [`Position::from_lsp`] to convert using the source text
+#[cfg(feature = "lsp")]
+impl From<lsp_types::Position> for Position {
+    fn from(value: lsp_types::Position) -> Self {
+        Self::new(value.line + 1, value.character + 1)
+    }
+}
+
+/// Converts the range as is, see the conversion of [`Position`] for the limitations. Use [`Range::to_lsp`] to convert using the source text
+#[cfg(feature = "lsp")]
+impl From<Range> for lsp_types::Range {
+    fn from(value: Range) -> Self {
+        Self::new(value.start.into(), value.end.into())
+    }
+}
+
+/// Converts the range as is, see the conversion of [`Position`] for the limitations. Use [`Range::from_lsp`] to convert using the source text
+#[cfg(feature = "lsp")]
+impl From<lsp_types::Range> for Range {
+    fn from(value: lsp_types::Range) -> Self {
+        Self {
+            start: value.start.into(),
+            end: value.end.into(),
+        }
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl Position {
+    /// Converts to an LSP position, with the column counted in UTF-16 code units of the line in `text`.
+    ///
+    /// `text` should be the text the position points into. Positions outside of `text` are converted as is
+    #[must_use]
+    pub fn to_lsp(self, text: &str) -> lsp_types::Position {
+        let character = line_text(text, self.line).map_or(self.col.saturating_sub(1), |line| {
+            line.chars()
+                .take(self.col.saturating_sub(1) as usize)
+                .map(char::len_utf16)
+                .sum::<usize>() as u32
+        });
+        lsp_types::Position::new(self.line.saturating_sub(1), character)
+    }
+
+    /// Converts from an LSP position, with the column counted in UTF-16 code units of the line in `text`.
+    ///
+    /// `text` should be the text the position points into. Positions outside of `text` are converted as is
+    #[must_use]
+    pub fn from_lsp(position: lsp_types::Position, text: &str) -> Self {
+        let line = position.line + 1;
+        let col = line_text(text, line).map_or(position.character, |line| {
+            let mut units = 0;
+            line.chars()
+                .take_while(|c| {
+                    units += c.len_utf16() as u32;
+                    units <= position.character
+                })
+                .count() as u32
+        });
+        Self::new(line, col + 1)
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl Range {
+    /// Converts to an LSP range, see [`Position::to_lsp`]
+    #[must_use]
+    pub fn to_lsp(self, text: &str) -> lsp_types::Range {
+        lsp_types::Range::new(self.start.to_lsp(text), self.end.to_lsp(text))
+    }
+
+    /// Converts from an LSP range, see [`Position::from_lsp`]
+    #[must_use]
+    pub fn from_lsp(range: lsp_types::Range, text: &str) -> Self {
+        Self {
+            start: Position::from_lsp(range.start, text),
+            end: Position::from_lsp(range.end, text),
+        }
+    }
+}
+
+/// The text of line number `line`, counted from 1
+#[cfg(feature = "lsp")]
+fn line_text(text: &str, line: u32) -> Option<&str> {
+    text.split('\n').nth(line.checked_sub(1)? as usize)
+}
+
 impl<'a> From<LocatedSpan<'a>> for Range {
     fn from(value: LocatedSpan) -> Self {
         let start = Position::from_located_span(&value);
         let delta_lines: u32 = value.fragment().chars().filter(|&c| c == '\n').count() as u32;
-        let last_line = value.fragment().split('\n').last();
+        let last_line = value.fragment().split('\n').next_back();
         let col: u32 = last_line.map_or(0, |ll| ll.chars().count() as u32);
         let end = Position {
             line: start.line + delta_lines,
@@ -355,11 +797,108 @@ impl Display for Error {
     }
 }
 
+impl std::error::Error for Error {}
+
+/// All the errors from parsing a text, like the errors returned by [`crate::Formatter::format_text()`].
+/// Implements [`std::error::Error`], so it can be used with `?`
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{parser::ParseErrors, Formatter, Indentation, LineReturn};
+///
+/// fn format(text: &str) -> Result<String, Box<dyn std::error::Error>> {
+///     let formatter = Formatter::new(Indentation::Tabs, None, LineReturn::LF);
+///     Ok(formatter.format_text(text).map_err(ParseErrors)?)
+/// }
+/// assert!(format("node {").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseErrors(pub Vec<Error>);
+
+impl Display for ParseErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.as_slice() {
+            [] => write!(f, "no parse errors"),
+            [error] => write!(f, "1 parse error: {error}"),
+            [first, ..] => write!(f, "{} parse errors, the first is: {first}", self.0.len()),
+        }
+    }
+}
+
+impl std::error::Error for ParseErrors {}
+
+impl From<Vec<Error>> for ParseErrors {
+    fn from(errors: Vec<Error>) -> Self {
+        Self(errors)
+    }
+}
+
+impl Error {
+    /// Same as the `Display` output, prefixed with the severity, like `warning: message, found 'source' at 1:2-5`
+    #[must_use]
+    pub fn display_with_severity(&self) -> String {
+        format!("{}: {self}", self.severity)
+    }
+
+    /// Renders the error together with the line of `source` it points to, underlining the range of the error.
+    ///
+    /// If the error spans multiple lines, only the first line is shown, underlined to the end of the line.
+    /// `source` should be the text that was parsed when the error was produced
+    #[must_use]
+    pub fn render(&self, source: &str) -> String {
+        let severity = &self.severity;
+        let Range { start, end } = self.range;
+        let line_number = start.line.to_string();
+        let pad = " ".repeat(line_number.len());
+        let mut output = format!(
+            "{severity}: {}\n{pad}--> {}:{}\n",
+            self.message, start.line, start.col
+        );
+        if let Some(line) = source.lines().nth((start.line as usize).saturating_sub(1)) {
+            let start_col = (start.col as usize).saturating_sub(1);
+            let end_col = if self.range.is_single_line() {
+                (end.col as usize).saturating_sub(1)
+            } else {
+                line.chars().count()
+            };
+            // Keep tabs in the padding, so the underline lines up with the text above it
+            let underline_pad: String = line
+                .chars()
+                .take(start_col)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let underline = "^".repeat(end_col.saturating_sub(start_col).max(1));
+            output.push_str(&format!(
+                "{pad} |\n{line_number} | {line}\n{pad} | {underline_pad}{underline}\n"
+            ));
+        }
+        if let Some(context) = &self.context {
+            output.push_str(&format!(
+                "{pad} = note: {} at {}\n",
+                context.inner,
+                context.get_range()
+            ));
+        }
+        output
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::parser::Range;
 
+    #[test]
+    fn test_render_error() {
+        let source = "node\n{\n\tkey = val\n\tbad stuff\n}\n";
+        let (_doc, errors) = crate::parser::parse(source);
+        assert_eq!(errors.len(), 1, "{errors:#?}");
+        assert_eq!(
+            errors[0].render(source),
+            "error: unexpected `bad stuff`\n --> 4:2\n  |\n4 | \tbad stuff\n  | \t^^^^^^^^^\n"
+        );
+    }
+
     #[test]
     fn test_ranges() {
         let ranges = vec![Range::new(0, 0, 0, 5), Range::new(0, 10, 0, 15)];
@@ -370,4 +909,204 @@ mod tests {
         let ranges_new = Range::combine_ranges(ranges.clone());
         assert_eq!(ranges_new, ranges_res);
     }
+
+    #[test]
+    fn test_ranges_strict() {
+        // Adjacent across a line boundary
+        let ranges = vec![Range::new(1, 5, 2, 3), Range::new(2, 3, 4, 1)];
+        assert_eq!(Range::combine_ranges_strict(ranges.clone()), ranges);
+        assert_eq!(Range::combine_ranges(ranges), vec![Range::new(1, 5, 4, 1)]);
+        // Overlapping across a line boundary
+        let ranges = vec![Range::new(2, 1, 3, 8), Range::new(1, 5, 2, 4)];
+        assert_eq!(
+            Range::combine_ranges_strict(ranges.clone()),
+            vec![Range::new(1, 5, 3, 8)]
+        );
+        assert_eq!(Range::combine_ranges(ranges), vec![Range::new(1, 5, 3, 8)]);
+        // Contained in another range
+        let ranges = vec![Range::new(1, 1, 5, 1), Range::new(2, 1, 2, 5)];
+        assert_eq!(
+            Range::combine_ranges_strict(ranges),
+            vec![Range::new(1, 1, 5, 1)]
+        );
+    }
+
+    #[test]
+    fn test_range_lines() {
+        let single_line = Range::new(2, 3, 2, 8);
+        assert_eq!(single_line.line_span(), 0);
+        assert!(single_line.is_single_line());
+        assert!(!single_line.is_empty());
+        assert_eq!(single_line.to_string(), "2:3-8");
+
+        let multi_line = Range::new(2, 3, 5, 1);
+        assert_eq!(multi_line.line_span(), 3);
+        assert!(!multi_line.is_single_line());
+        assert!(!multi_line.is_empty());
+        assert_eq!(multi_line.to_string(), "[2, 3] to [5, 1]");
+
+        let empty = Range::new(4, 7, 4, 7);
+        assert_eq!(empty.line_span(), 0);
+        assert!(empty.is_single_line());
+        assert!(empty.is_empty());
+        assert!(single_line.to_start().is_empty());
+    }
+
+    #[test]
+    fn test_range_from_offsets() {
+        let text = "ab\ncé\r\n\nd";
+        assert_eq!(Range::from_offsets(text, 0, 2), Range::new(1, 1, 1, 3));
+        // The line ending belongs to the line it ends
+        assert_eq!(Range::from_offsets(text, 2, 3), Range::new(1, 3, 2, 1));
+        // Columns count chars, not bytes
+        assert_eq!(Range::from_offsets(text, 3, 6), Range::new(2, 1, 2, 3));
+        assert_eq!(Range::from_offsets(text, 8, 8), Range::new(3, 1, 3, 1));
+        // The end of the file, and past it
+        assert_eq!(
+            Range::from_offsets(text, 9, text.len()),
+            Range::new(4, 1, 4, 2)
+        );
+        assert_eq!(Range::from_offsets(text, 100, 200), Range::new(4, 2, 4, 2));
+        // Inside `é`, which is two bytes
+        assert_eq!(Range::from_offsets(text, 5, 6), Range::new(2, 2, 2, 3));
+        // Ranges created by the parser agree
+        let text = "node { } leftover\n";
+        let (_doc, errors) = super::parse(text);
+        let start = text.find("leftover").unwrap();
+        assert_eq!(
+            errors[0].range,
+            Range::from_offsets(text, start, start + "leftover".len())
+        );
+    }
+
+    #[test]
+    fn test_parse_snippets() {
+        let key_val = crate::parser::parse_key_val("key = val").unwrap();
+        assert_eq!((*key_val.key, &**key_val.val), ("key", "val"));
+        assert_eq!(key_val.get_range(), Range::new(1, 1, 1, 10));
+
+        let node = crate::parser::parse_node("node\n{\n\tkey = val\n\tchild {}\n}\n").unwrap();
+        assert_eq!(*node.identifier, "node");
+        assert_eq!(node.block.len(), 2);
+
+        // Only a single item is allowed
+        let errors = crate::parser::parse_key_val("key = val\nother = val").unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "unexpected `other = val` after assignment"
+        );
+        assert!(crate::parser::parse_node("a {}\nb {}").is_err());
+        assert!(crate::parser::parse_node("key = val").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors_display() {
+        use crate::parser::{parse, ParseErrors};
+        assert_eq!(ParseErrors(vec![]).to_string(), "no parse errors");
+        let errors = ParseErrors(parse("a {\n").1);
+        assert_eq!(
+            errors.to_string(),
+            format!("1 parse error: {}", errors.0[0])
+        );
+        let errors = ParseErrors::from(parse("a {\n} }\n{\n").1);
+        assert!(errors.0.len() > 1, "{errors:#?}");
+        assert_eq!(
+            errors.to_string(),
+            format!(
+                "{} parse errors, the first is: {}",
+                errors.0.len(),
+                errors.0[0]
+            )
+        );
+        // Usable as a boxed error
+        let boxed: Box<dyn std::error::Error> = Box::new(errors);
+        assert!(boxed.to_string().contains("parse errors"));
+    }
+
+    #[test]
+    fn test_display_with_severity() {
+        use crate::parser::{Error, Severity};
+        for (severity, prefix) in [
+            (Severity::Error, "error: "),
+            (Severity::Warning, "warning: "),
+            (Severity::Info, "info: "),
+            (Severity::Hint, "hint: "),
+        ] {
+            let error = Error {
+                severity,
+                code: crate::parser::codes::UNEXPECTED_TEXT,
+                range: Range::new(1, 2, 1, 5),
+                source: "bad".to_owned(),
+                message: "message".to_owned(),
+                context: None,
+            };
+            assert_eq!(
+                error.display_with_severity(),
+                format!("{prefix}message, found 'bad' at 1:2-5")
+            );
+        }
+    }
+
+    #[test]
+    fn test_ranged_into_inner() {
+        let key_val = super::parse_key_val("key = val").unwrap();
+        let range = key_val.get_range();
+        let (inner, parts_range) = key_val.clone().into_parts();
+        assert_eq!(parts_range, range);
+        let key_val = key_val.into_inner();
+        assert_eq!(*key_val.key, "key");
+        assert_eq!(*key_val.val, "val");
+        assert!(key_val.eq_ignore_ranges(&inner));
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_lsp_conversion() {
+        // `key` in the third line, after a tab
+        let range = Range::new(3, 2, 3, 5);
+        let lsp_range = lsp_types::Range::from(range);
+        assert_eq!(
+            lsp_range,
+            lsp_types::Range::new(
+                lsp_types::Position::new(2, 1),
+                lsp_types::Position::new(2, 4)
+            )
+        );
+        assert_eq!(Range::from(lsp_range), range);
+        assert_eq!(
+            crate::parser::Position::from(lsp_types::Position::new(0, 0)),
+            crate::parser::Position::new(1, 1)
+        );
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_lsp_utf16_columns() {
+        use crate::parser::Position;
+        // `🚀` is one char, but two UTF-16 code units
+        let text = "🚀é {\n\tkey = 🚀x\n}\n";
+        let range = Range::new(2, 8, 2, 10);
+        let lsp_range = range.to_lsp(text);
+        assert_eq!(
+            lsp_range,
+            lsp_types::Range::new(
+                lsp_types::Position::new(1, 7),
+                lsp_types::Position::new(1, 10)
+            )
+        );
+        assert_eq!(Range::from_lsp(lsp_range, text), range);
+        assert_eq!(
+            Position::new(1, 3).to_lsp(text),
+            lsp_types::Position::new(0, 3)
+        );
+        assert_eq!(
+            Position::from_lsp(lsp_types::Position::new(0, 3), text),
+            Position::new(1, 3)
+        );
+        // Past the end of the text, the position is converted as is
+        assert_eq!(
+            Position::new(9, 4).to_lsp(text),
+            lsp_types::Position::new(8, 3)
+        );
+    }
 }
@@ -3,21 +3,22 @@ use std::fmt::Display;
 use itertools::Itertools;
 use nom::{
     branch::alt,
-    bytes::complete::{is_a, tag_no_case},
-    character::complete::{char, one_of},
+    bytes::complete::{is_a, is_not, tag_no_case},
+    character::complete::{char, one_of, space1},
     combinator::{map, opt, recognize},
-    multi::{many1, separated_list1},
+    multi::{many0, many1, separated_list1},
     sequence::{delimited, pair},
 };
 use nom_unicode::complete::alphanumeric1;
 
 use super::{
-    parser_helpers::{expect, range_wrap},
-    Ranged, {ASTParse, IResult, LocatedSpan},
+    codes,
+    parser_helpers::{expect, get_range, range_wrap},
+    Error, Ranged, Severity, {ASTParse, IResult, LocatedSpan},
 };
 
 /// Contains a `Vec` of all the clauses to be combined using logical ANDs. All clauses have to be satisfied for the parent operation to be executed
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NeedsBlock<'a> {
     /// The clauses to be combined using logical ANDs
     pub or_clauses: Vec<Ranged<OrClause<'a>>>,
@@ -30,7 +31,7 @@ impl<'a> Display for NeedsBlock<'a> {
 }
 
 /// Contains a `Vec` of all the clauses to be combined using logical ORs. If any of those clauses are satisfied, the clause is satisfied.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OrClause<'a> {
     /// The clauses to be combined using logical ORs
     pub mod_clauses: Vec<Ranged<ModClause<'a>>>,
@@ -43,7 +44,7 @@ impl<'a> Display for OrClause<'a> {
 }
 
 /// A mod that is needed (or not) for the clause to be satisfied
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ModClause<'a> {
     /// If true, the mod should not be present for the clause to be satisfied
     pub negated: bool,
@@ -58,16 +59,21 @@ impl<'a> Display for ModClause<'a> {
 }
 
 impl<'a> ASTParse<'a> for NeedsBlock<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<NeedsBlock<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<NeedsBlock<'a>>> {
         // needsBlock = { ^":NEEDS[" ~ modOrClause ~ (("&" | ",") ~ modOrClause)* ~ "]" }
         range_wrap(map(
             delimited(
                 tag_no_case(":NEEDS["),
                 expect(
                     separated_list1(one_of("&,"), OrClause::parse),
+                    codes::EXPECTED_TOKEN,
                     "Expected AND'ed mod",
                 ),
-                expect(tag_no_case("]"), "Expected closing `]`"),
+                expect(
+                    tag_no_case("]"),
+                    codes::UNCLOSED_BRACKET,
+                    "Expected closing `]`",
+                ),
             ),
             |inner| NeedsBlock {
                 or_clauses: inner.unwrap_or_default(),
@@ -77,11 +83,15 @@ impl<'a> ASTParse<'a> for NeedsBlock<'a> {
 }
 
 impl<'a> ASTParse<'a> for OrClause<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<OrClause<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<OrClause<'a>>> {
         // modOrClause = { needsMod ~ ("|" ~ needsMod)* }
         range_wrap(map(
             expect(
-                separated_list1(one_of("|"), expect(ModClause::parse, "Expected mod")),
+                separated_list1(
+                    one_of("|"),
+                    expect(ModClause::parse, codes::EXPECTED_TOKEN, "Expected mod"),
+                ),
+                codes::EXPECTED_TOKEN,
                 "Expected OR'd mods",
             ),
             |inner| {
@@ -97,20 +107,63 @@ impl<'a> ASTParse<'a> for OrClause<'a> {
 }
 
 impl<'a> ASTParse<'a> for ModClause<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<ModClause<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<ModClause<'a>>> {
         // needsMod    = { negation? ~ modName }
         // negation    = { "!" }
         let negated = opt(char::<LocatedSpan, _>('!'));
-        // modName = { (LETTER | ASCII_DIGIT | "/" | "_" | "-" | "?")+ }
-        let mod_name = recognize(many1(alt((alphanumeric1, is_a("/_-?")))));
         let mod_clause = pair(negated, mod_name);
         range_wrap(map(mod_clause, |inner| ModClause {
             negated: inner.0.is_some(),
-            name: inner.1.fragment(),
+            name: inner.1,
         }))(input)
     }
 }
 
+/// Parses the name of a mod. Spaces and version qualifiers, like `mod:1.2`, are not allowed in the name, but are parsed to report a helpful error
+fn mod_name(input: LocatedSpan<'_>) -> IResult<'_, &str> {
+    // modName = { (LETTER | ASCII_DIGIT | "/" | "_" | "-" | "?")+ }
+    let name_part = || recognize(many1(alt((alphanumeric1, is_a("/_-?")))));
+    let start = input.clone();
+    let (input, (name, range)) = get_range(recognize(pair(
+        name_part(),
+        many0(pair(space1, name_part())),
+    )))(input)?;
+    let (input, qualifier) = opt(get_range(recognize(pair(
+        char(':'),
+        opt(is_not("]|&,\r\n")),
+    ))))(input)?;
+    let name: &str = name.fragment();
+    if let Some((qualifier, qualifier_range)) = qualifier {
+        input.extra.report_error(Error {
+            severity: Severity::Error,
+            code: codes::NEEDS_VERSION,
+            range: qualifier_range,
+            source: qualifier.fragment().to_string(),
+            message: format!(
+                "version qualifiers are not supported in `:NEEDS`. Only the presence of `{name}` can be checked"
+            ),
+            context: None,
+        });
+        // Keep the qualifier as part of the name, so the text is printed as is
+        let full_len = start.fragment().len() - input.fragment().len();
+        return Ok((input, &start.fragment()[..full_len]));
+    }
+    if name.contains([' ', '\t']) {
+        input.extra.report_error(Error {
+            severity: Severity::Error,
+            code: codes::BAD_MOD_NAME,
+            range,
+            source: name.to_owned(),
+            message: format!(
+                "mod names can not contain spaces. Did you mean `{}`?",
+                name.split_whitespace().join("_")
+            ),
+            context: None,
+        });
+    }
+    Ok((input, name))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -125,17 +178,65 @@ mod tests {
         match res {
             Ok(it) => {
                 let errors = it.0.extra.errors.borrow().clone();
-                if errors.len() > 0 {
+                if !errors.is_empty() {
                     for error in &errors {
                         eprintln!("{:#?}", error);
                     }
                 }
                 assert_eq!(input, it.1.to_string());
-                if errors.len() > 0 {
+                if !errors.is_empty() {
                     panic!()
                 }
             }
             Err(err) => panic!("{}", err),
         }
     }
+
+    #[test]
+    fn test_needs_with_space() {
+        let input = "@PART:NEEDS[My Mod] {}\n";
+        let (doc, errors) = crate::parser::parse(input);
+        assert_eq!(errors.len(), 1, "{errors:#?}");
+        assert_eq!(errors[0].severity, Severity::Error);
+        assert_eq!(
+            errors[0].message,
+            "mod names can not contain spaces. Did you mean `My_Mod`?"
+        );
+        assert_eq!(errors[0].range, crate::parser::Range::new(1, 13, 1, 19));
+        // The text is kept as is
+        assert_eq!(
+            crate::parser::ASTPrint::ast_print(
+                &doc,
+                0,
+                "\t",
+                "\n",
+                &crate::parser::PrintSettings::new(None)
+            ),
+            input
+        );
+    }
+
+    #[test]
+    fn test_needs_with_version() {
+        let input = "@PART:NEEDS[mod:1.2,other] {}\n";
+        let (doc, errors) = crate::parser::parse(input);
+        assert_eq!(errors.len(), 1, "{errors:#?}");
+        assert_eq!(errors[0].severity, Severity::Error);
+        assert_eq!(
+            errors[0].message,
+            "version qualifiers are not supported in `:NEEDS`. Only the presence of `mod` can be checked"
+        );
+        assert_eq!(errors[0].range, crate::parser::Range::new(1, 16, 1, 20));
+        // The text is kept as is
+        assert_eq!(
+            crate::parser::ASTPrint::ast_print(
+                &doc,
+                0,
+                "\t",
+                "\n",
+                &crate::parser::PrintSettings::new(None)
+            ),
+            input
+        );
+    }
 }
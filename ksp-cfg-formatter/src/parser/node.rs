@@ -4,32 +4,35 @@ use nom::bytes::complete::{is_a, is_not};
 use nom::character::complete::{anychar, char, line_ending, multispace1, one_of, space0};
 use nom::combinator::{all_consuming, consumed, map, opt, peek, recognize};
 use nom::multi::{many0, many1, many_till, separated_list0};
-use nom::sequence::{delimited, preceded, tuple};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::InputTake;
 use nom_unicode::complete::alphanumeric1;
+use std::borrow::Cow;
 
 use super::parser_helpers::ignore_line_ending;
 use super::parser_helpers::{
-    debug_fn, empty_line, error_till, expect, expect_context, get_range, non_empty, range_wrap, ws,
-    ws_le,
+    debug_fn, empty_line, error_till, expect, expect_context, get_range, non_empty, printed_eq,
+    range_wrap, ws, ws_le,
 };
 use super::{IResult, LocatedSpan};
 
 use super::Ranged;
 use super::{
-    ASTParse, ASTPrint, Comment, HasBlock, Index, KeyVal, NeedsBlock, NodeItem, Operator, Pass,
-    Path, Range,
+    block_comment, codes, ASTParse, ASTPrint, BraceStyle, Comment, DocStats, Error, HasBlock,
+    Index, KeyVal, NeedsBlock, NodeItem, Operator, ParserState, Pass, Path, Position,
+    PrintSettings, Range, Severity,
 };
 
 /// A node in the config file. Both top level node and internal node
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Node<'a> {
     top_level: bool,
     /// Optional path to node, only allowed on internal nodes
     pub path: Option<Ranged<Path<'a>>>,
     /// Optional operator
     pub operator: Option<Ranged<Operator>>,
-    /// Identifier of the node
-    pub identifier: Ranged<&'a str>,
+    /// Identifier of the node. Borrowed from the parsed text, unless replaced by a transformer
+    pub identifier: Ranged<Cow<'a, str>>,
     /// Optional name of the node. Same as `:HAS[name[<name>]]`
     pub name: Option<Ranged<Vec<&'a str>>>,
     /// Optional HAS block
@@ -57,8 +60,84 @@ impl<'a> Node<'a> {
     pub const fn top_level(&self) -> bool {
         self.top_level
     }
+    /// The complete identifier of the node as printed, including path, operator, name, HAS, pass, NEEDS and index, e.g. `@PART[foo]:HAS[#key]:FOR[mod]`
+    ///
+    /// The HAS, pass and NEEDS clauses can be given in any order in the input, but are always printed in this canonical order,
+    /// so `@PART:NEEDS[mod]:HAS[#key]` is printed as `@PART:HAS[#key]:NEEDS[mod]`
+    #[must_use]
+    pub fn header(&self) -> String {
+        format!(
+            "{}{}{}{}{}{}{}{}{}",
+            if self.path.is_some() { "#" } else { "" },
+            self.path.clone().map_or(String::new(), |p| p.to_string()),
+            self.operator.clone().unwrap_or_default(),
+            *self.identifier,
+            self.name.clone().map_or(String::new(), |name| format!(
+                "[{}]",
+                name.iter().format("|")
+            )),
+            self.has.clone().unwrap_or_default(),
+            self.pass.clone().map_or(String::new(), |p| p.to_string()),
+            self.needs.clone().map_or(String::new(), |n| n.to_string()),
+            self.index
+                .as_deref()
+                .map_or(String::new(), std::string::ToString::to_string),
+        )
+    }
+    pub(crate) fn set_top_level(&mut self, top_level: bool) {
+        self.top_level = top_level;
+    }
+    /// Prints the node on its own, without needing to import [`ASTPrint`]. `collapse` is used like the `inline` setting of [`crate::Formatter::new()`]
+    ///
+    /// Example:
+    /// ```
+    /// use ksp_cfg_formatter::parser::{parse, DocItem};
+    ///
+    /// let (doc, _errors) = parse("node {   key   =   val }");
+    /// let DocItem::Node(node) = &doc.statements[0] else { panic!() };
+    /// assert_eq!(node.to_string_with("\t", "\n", Some(false)), "node\n{\n\tkey = val\n}\n");
+    /// assert_eq!(node.to_string_with("\t", "\n", Some(true)), "node { key = val }\n");
+    /// ```
+    #[must_use]
+    pub fn to_string_with(
+        &self,
+        indentation: &str,
+        line_ending: &str,
+        collapse: Option<bool>,
+    ) -> String {
+        self.ast_print(0, indentation, line_ending, &PrintSettings::new(collapse))
+    }
+    /// Indicates if the node is a Module Manager patch, meaning it has an operator or a path
+    #[must_use]
+    pub const fn is_patch(&self) -> bool {
+        self.operator.is_some() || self.path.is_some()
+    }
+    /// Indicates if the node inserts a new node, meaning it has no operator and no path.
+    /// A node with only a path copies the node at the path, so it is not an insert
+    #[must_use]
+    pub fn is_insert(&self) -> bool {
+        self.path.is_none()
+            && self
+                .operator
+                .as_ref()
+                .is_none_or(|op| matches!(op.as_ref(), Operator::None))
+    }
+    /// Indicates if the node deletes a node, using `!` or `-`
+    #[must_use]
+    pub fn is_delete(&self) -> bool {
+        self.operator
+            .as_ref()
+            .is_some_and(|op| matches!(op.as_ref(), Operator::Delete | Operator::DeleteAlt))
+    }
+    /// Indicates if the node edits a node, using `@` or `%`
+    #[must_use]
+    pub fn is_edit(&self) -> bool {
+        self.operator
+            .as_ref()
+            .is_some_and(|op| matches!(op.as_ref(), Operator::Edit | Operator::EditOrCreate))
+    }
     /// Returns an iterator over all of the Nodes contained within this node
-    pub fn iter_nodes(&self) -> impl Iterator<Item = &Ranged<Node>> {
+    pub fn iter_nodes(&self) -> impl Iterator<Item = &Ranged<Node<'_>>> {
         self.block.iter().filter_map(|n| {
             if let NodeItem::Node(node) = n {
                 Some(node)
@@ -68,7 +147,7 @@ impl<'a> Node<'a> {
         })
     }
     /// Returns an iterator over all of the Assignments contained within this node
-    pub fn iter_keyvals(&self) -> impl Iterator<Item = &Ranged<KeyVal>> {
+    pub fn iter_keyvals(&self) -> impl Iterator<Item = &Ranged<KeyVal<'_>>> {
         self.block.iter().filter_map(|n| {
             if let NodeItem::KeyVal(kv) = n {
                 Some(kv)
@@ -77,6 +156,127 @@ impl<'a> Node<'a> {
             }
         })
     }
+    /// Returns an iterator over the Assignments in this node with the key `key`. Operators, indices and paths are not part of the key
+    pub fn keyvals_named<'b>(
+        &'b self,
+        key: &'b str,
+    ) -> impl Iterator<Item = &'b Ranged<KeyVal<'b>>> {
+        self.iter_keyvals().filter(move |kv| *kv.key == key)
+    }
+    /// Returns the first Assignment in this node with the key `key`. See [`Node::keyvals_named()`]
+    #[must_use]
+    pub fn first_keyval(&self, key: &str) -> Option<&Ranged<KeyVal<'_>>> {
+        self.iter_keyvals().find(|kv| *kv.key == key)
+    }
+    /// Compares two nodes, including everything inside them, ignoring the ranges of all items and any print settings set by a transformer.
+    /// Useful when the same content has been parsed from differently formatted text
+    #[must_use]
+    pub fn eq_ignore_ranges(&self, other: &Self) -> bool {
+        self.top_level == other.top_level
+            && printed_eq(&self.path, &other.path)
+            && self.operator.as_deref() == other.operator.as_deref()
+            && *self.identifier == *other.identifier
+            && self.name.as_deref() == other.name.as_deref()
+            && printed_eq(&self.has, &other.has)
+            && printed_eq(&self.needs, &other.needs)
+            && self.pass.as_deref() == other.pass.as_deref()
+            && self.index.as_deref() == other.index.as_deref()
+            && self.id_comment.as_deref() == other.id_comment.as_deref()
+            && self
+                .comments_after_newline
+                .iter()
+                .map(AsRef::as_ref)
+                .eq(other.comments_after_newline.iter().map(AsRef::as_ref))
+            && self.block.len() == other.block.len()
+            && self
+                .block
+                .iter()
+                .zip(&other.block)
+                .all(|(a, b)| a.eq_ignore_ranges(b))
+            && self.trailing_comment.as_deref() == other.trailing_comment.as_deref()
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Adds the counts of this node and everything inside it to `stats`
+    pub(crate) fn add_stats(&self, stats: &mut DocStats) {
+        stats.nodes += 1;
+        stats.comments += self.comments_after_newline.len()
+            + usize::from(self.id_comment.is_some())
+            + usize::from(self.trailing_comment.is_some());
+        let mut after_error = false;
+        for item in &self.block {
+            match item {
+                NodeItem::Node(node) => node.add_stats(stats),
+                NodeItem::KeyVal(key_val) => {
+                    stats.keyvals += 1;
+                    stats.comments += usize::from(key_val.comment.is_some());
+                }
+                NodeItem::Comment(_) => stats.comments += 1,
+                // The line ending after an error is parsed as an empty line
+                NodeItem::EmptyLine(_) if after_error => (),
+                NodeItem::EmptyLine(_) => stats.empty_lines += 1,
+                NodeItem::Error(_) => stats.errors += 1,
+            }
+            after_error = matches!(item, NodeItem::Error(_));
+        }
+    }
+
+    /// The node as an S-expression. See [`super::Document::to_sexp()`]
+    pub(crate) fn to_sexp(&self) -> String {
+        let mut parts = vec![
+            "node".to_owned(),
+            format!(
+                "{:?}",
+                format!(
+                    "{}{}{}{}",
+                    if self.path.is_some() { "#" } else { "" },
+                    self.path.clone().map_or(String::new(), |p| p.to_string()),
+                    self.operator.clone().unwrap_or_default(),
+                    self.identifier
+                )
+            ),
+        ];
+        if let Some(name) = &self.name {
+            parts.push(format!("[{}]", name.iter().format("|")));
+        }
+        parts.extend(self.has.iter().map(ToString::to_string));
+        parts.extend(self.pass.iter().map(ToString::to_string));
+        parts.extend(self.needs.iter().map(ToString::to_string));
+        parts.extend(self.index.iter().map(|index| index.to_string()));
+        for item in &self.block {
+            parts.push(match item {
+                NodeItem::Node(node) => node.to_sexp(),
+                NodeItem::KeyVal(key_val) => format!(
+                    "(keyval {:?} {:?} {:?})",
+                    key_val.left_side(),
+                    key_val.assignment_operator.to_string(),
+                    *key_val.val
+                ),
+                NodeItem::Comment(comment) => format!("(comment {:?})", comment.text.trim_start()),
+                NodeItem::EmptyLine(_) => "(empty_line)".to_owned(),
+                NodeItem::Error(error) => format!("(error {:?})", **error),
+            });
+        }
+        format!("({})", parts.join(" "))
+    }
+
+    /// Adds the passes referring to a mod from this node and all child nodes to `references`
+    pub(crate) fn pass_references(&self, references: &mut Vec<(Pass<'a>, Range)>) {
+        if let Some(pass) = &self.pass {
+            if matches!(
+                pass.as_ref(),
+                Pass::Before(_) | Pass::For(_) | Pass::After(_) | Pass::Last(_)
+            ) {
+                references.push((**pass, pass.get_range()));
+            }
+        }
+        for item in &self.block {
+            if let NodeItem::Node(node) = item {
+                node.pass_references(references);
+            }
+        }
+    }
 }
 
 impl<'a> ASTPrint for Node<'a> {
@@ -85,83 +285,74 @@ impl<'a> ASTPrint for Node<'a> {
         depth: usize,
         indentation: &str,
         line_ending: &str,
-        should_collapse: Option<bool>,
+        settings: &PrintSettings,
     ) -> String {
         let mut output = String::new();
         for comment in &self.comments_after_newline {
             output.push_str(
                 comment
-                    .ast_print(depth, indentation, line_ending, should_collapse)
+                    .ast_print(depth, indentation, line_ending, settings)
                     .as_str(),
             );
         }
         let indentation_str = indentation.repeat(depth);
-        let complete_node_name = format!(
-            "{}{}{}{}{}{}{}{}{}",
-            if self.path.is_some() { "#" } else { "" },
-            self.path.clone().map_or(String::new(), |p| p.to_string()),
-            self.operator.clone().unwrap_or_default(),
-            self.identifier,
-            self.name.clone().map_or(String::new(), |name| format!(
-                "[{}]",
-                name.iter().format("|")
-            )),
-            self.has.clone().unwrap_or_default(),
-            self.pass.clone().map_or(String::new(), |p| p.to_string()),
-            self.needs.clone().map_or(String::new(), |n| n.to_string()),
-            self.index
-                .as_deref()
-                .map_or(String::new(), std::string::ToString::to_string),
-        );
+        let complete_node_name = self.header();
+        // The comment after the closing bracket always has exactly one space before it
+        let trailing_comment = self
+            .trailing_comment
+            .as_ref()
+            .map(|c| format!(" {}", c.print_text(settings).trim_start()))
+            .unwrap_or_default();
         output.push_str(
             match self.block.len() {
                 0 if self.id_comment.is_none() => {
                     format!(
-                        "{}{} {{}}{}{}",
+                        "{}{} {}{}{}",
                         indentation_str,
                         complete_node_name,
-                        self.trailing_comment
-                            .as_ref()
-                            .map_or_else(|| "", |c| c.text),
+                        if settings.space_in_empty_braces {
+                            "{ }"
+                        } else {
+                            "{}"
+                        },
+                        trailing_comment,
                         line_ending
                     )
                 }
-                1 if should_collapse.unwrap_or(self.was_collapsed) && short_node(self) => {
+                1 if settings.should_collapse.unwrap_or(self.was_collapsed) && short_node(self, settings.max_line_length) => {
                     format!(
                         "{}{} {{ {} }}{}{}",
                         indentation_str,
                         complete_node_name,
-                        self.block[0].ast_print(0, indentation, "", should_collapse),
-                        self.trailing_comment
-                            .as_ref()
-                            .map_or_else(|| "", |c| c.text),
+                        self.block[0].ast_print(0, indentation, "", settings),
+                        trailing_comment,
                         line_ending
                     )
                 }
                 _ => {
-                    let mut output = format!(
-                        "{}{}{}{}{}{{{}",
-                        indentation_str,
-                        complete_node_name,
-                        self.id_comment.as_ref().map_or_else(|| "", |c| c.text),
-                        line_ending,
-                        indentation_str,
-                        line_ending
-                    );
+                    let id_comment = self
+                        .id_comment
+                        .as_ref()
+                        .map(|c| c.print_text(settings))
+                        .unwrap_or_default();
+                    let mut output = match settings.brace_style {
+                        BraceStyle::OwnLine => format!(
+                            "{indentation_str}{complete_node_name}{id_comment}{line_ending}{indentation_str}{{{line_ending}"
+                        ),
+                        BraceStyle::SameLine => format!(
+                            "{indentation_str}{complete_node_name} {{{id_comment}{line_ending}"
+                        ),
+                    };
                     for statement in &self.block {
                         output.push_str(
                             statement
-                                .ast_print(depth + 1, indentation, line_ending, should_collapse)
+                                .ast_print(depth + 1, indentation, line_ending, settings)
                                 .as_str(),
                         );
                     }
                     output.push_str(&indentation_str);
                     output.push('}');
-                    output.push_str(
-                        self.trailing_comment
-                            .as_ref()
-                            .map_or_else(|| "", |c| c.text),
-                    );
+                    output.push_str(&trailing_comment);
                     output.push_str(line_ending);
                     output
                 }
@@ -174,8 +365,7 @@ impl<'a> ASTPrint for Node<'a> {
 
 // TODO: replace with just fetching the Range of the node
 // Doesn't work. The node could be multi line before parsing, and the ast_print function isn't available since that is recursion
-fn short_node(arg: &Node) -> bool {
-    const MAX_LENGTH: usize = 72;
+fn short_node(arg: &Node, max_line_length: usize) -> bool {
     if arg.id_comment.is_some() {
         return false;
     }
@@ -224,24 +414,31 @@ fn short_node(arg: &Node) -> bool {
         }
         _ => return false,
     }
-    len <= MAX_LENGTH
+    len <= max_line_length
 }
 
 impl<'a> ASTParse<'a> for Node<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<Node<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<Node<'a>>> {
         log::trace!("Entered node parser with input:\n{input}");
         let top_level = input.extra.state.top_level;
 
-        let parser = move |input| {
+        let parser = move |input: LocatedSpan<'a>| {
+            // A bracket without a node name is left for the error recovery of the surrounding block
+            let (_, first_part) = peek(identifier_part)(input.clone())?;
+            if first_part.fragment().trim().is_empty() {
+                return Err(nom::Err::Error(nom::error::Error {
+                    input,
+                    code: nom::error::ErrorKind::Fail,
+                }));
+            }
             let (input, dumb_identifier) = recognize(tuple((
-                many_till(
-                    anychar,
-                    peek(alt((
-                        recognize(Comment::parse),
-                        recognize(preceded(space0, one_of("{}\r\n"))),
-                    ))),
-                ),
-                many0(alt((recognize(Comment::parse), recognize(multispace1)))),
+                identifier_part,
+                identifier_trivia,
+                // The identifier might continue after a comment, which is reported when parsing the identifier
+                many0(pair(
+                    recognize(pair(one_of("[:"), identifier_part)),
+                    identifier_trivia,
+                )),
             )))(input)?;
 
             log::trace!("dumb identifier:\n{dumb_identifier}");
@@ -264,7 +461,7 @@ impl<'a> ASTParse<'a> for Node<'a> {
                 top_level,
                 path: complete_identifier.0,
                 operator: complete_identifier.1,
-                identifier: complete_identifier.2,
+                identifier: complete_identifier.2.map(Cow::Borrowed),
                 name: complete_identifier.3,
                 has: complete_identifier.4,
                 needs: complete_identifier.5,
@@ -272,8 +469,8 @@ impl<'a> ASTParse<'a> for Node<'a> {
                 index: complete_identifier.7,
                 id_comment: complete_identifier.8,
                 comments_after_newline: complete_identifier.9,
-                block: block.clone(),
-                trailing_comment: trailing_comment.clone(),
+                block,
+                trailing_comment,
                 was_collapsed,
             };
             for err in errors {
@@ -285,6 +482,25 @@ impl<'a> ASTParse<'a> for Node<'a> {
     }
 }
 
+/// Anything on the current line that could potentially be part of the identifier
+fn identifier_part(input: LocatedSpan) -> IResult<LocatedSpan> {
+    recognize(many_till(
+        anychar,
+        peek(alt((
+            recognize(Comment::parse),
+            recognize(preceded(space0, one_of("{}\r\n"))),
+        ))),
+    ))(input)
+}
+
+/// Comments and whitespace between the identifier and the opening bracket
+fn identifier_trivia(input: LocatedSpan) -> IResult<LocatedSpan> {
+    recognize(many0(alt((
+        recognize(Comment::parse),
+        recognize(multispace1),
+    ))))(input)
+}
+
 enum HasPassNeedsIndex<'a> {
     Has(Ranged<HasBlock<'a>>),
     Pass(Ranged<Pass<'a>>),
@@ -297,7 +513,8 @@ fn dumb_identifier_parser(dumb_identifier: LocatedSpan) -> (ParsedIdentifier, Ve
     dumb_identifier.extra.errors.borrow_mut().clear();
 
     let path = opt(preceded(char('#'), Path::parse));
-    let operator = opt(Operator::parse);
+    // Spaces after the operator are allowed, and dropped when printing
+    let operator = opt(terminated(Operator::parse, space0));
     // identifier = ${ ("-" | "_" | "." | "+" | "*" | "?" | LETTER | ASCII_DIGIT)+ }
     let identifier = range_wrap(map(
         recognize(many1(alt((
@@ -334,34 +551,48 @@ fn dumb_identifier_parser(dumb_identifier: LocatedSpan) -> (ParsedIdentifier, Ve
             rest.extra.errors.borrow_mut().clone(),
         ),
         // If an error is encountered, just stuff the pseudo-identifier inside the identifier, and report the error
-        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => (
-            (
-                None,
-                None,
-                dumb_identifier.into(),
-                None,
-                None,
-                None,
-                None,
-                None,
-                None,
-                vec![],
-            ),
-            vec![super::Error {
-                message: format!(
+        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+            let message = if comment_before(&dumb_identifier, &error.input) {
+                "comments are not allowed between the parts of a node identifier, move it after the identifier".to_owned()
+            } else {
+                format!(
                     "failed to parse identifier. Unexpected `{}`",
                     error.input.fragment()
+                )
+            };
+            (
+                (
+                    None,
+                    None,
+                    dumb_identifier.into(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    vec![],
                 ),
-                source: (*error.input.fragment()).to_string(),
-                range: Range::from(error.input),
-                severity: super::Severity::Error,
-                context: None,
-            }],
-        ),
+                vec![super::Error {
+                    code: super::codes::BAD_IDENTIFIER,
+                    message,
+                    source: (*error.input.fragment()).to_string(),
+                    range: Range::from(error.input),
+                    severity: super::Severity::Error,
+                    context: None,
+                }],
+            )
+        }
         _ => unreachable!(),
     }
 }
 
+/// Checks if there is a comment in `identifier` before the position of `rest`
+fn comment_before(identifier: &LocatedSpan, rest: &LocatedSpan) -> bool {
+    let offset = rest.location_offset() - identifier.location_offset();
+    identifier.fragment()[..offset].contains("//")
+}
+
 type ParsedIdentifier<'a> = (
     Option<Ranged<Path<'a>>>,
     Option<Ranged<Operator>>,
@@ -413,6 +644,7 @@ fn map_correct_identifier<'a>(
     if has_vec.len() > 1 {
         for has in &has_vec[1..] {
             rest.extra.report_error(super::Error {
+                code: super::codes::DUPLICATE_BLOCK,
                 message: "Got extra HAS block".to_owned(),
                 range: has.range,
                 source: has.to_string(),
@@ -426,6 +658,7 @@ fn map_correct_identifier<'a>(
     if needs_vec.len() > 1 {
         for needs in &needs_vec[1..] {
             rest.extra.report_error(super::Error {
+                code: super::codes::DUPLICATE_BLOCK,
                 message: "Got extra NEEDS block".to_owned(),
                 range: needs.range,
                 source: needs.to_string(),
@@ -436,14 +669,18 @@ fn map_correct_identifier<'a>(
     }
     let needs = needs_vec.first().cloned();
 
-    if pass_vec.len() > 1 {
-        for pass in &pass_vec[1..] {
+    if let Some((first, extra)) = pass_vec.split_first() {
+        for pass in extra {
             rest.extra.report_error(super::Error {
-                message: "Got extra PASS block".to_owned(),
+                code: super::codes::DUPLICATE_BLOCK,
+                message: format!(
+                    "a patch can only run in one pass, but `{pass}` is given in addition to `{}`",
+                    **first
+                ),
                 range: pass.range,
                 source: pass.to_string(),
                 severity: super::Severity::Error,
-                context: None,
+                context: Some(Ranged::new("First pass given here".to_owned(), first.range)),
             });
         }
     }
@@ -464,12 +701,13 @@ fn map_correct_identifier<'a>(
     )
 }
 
-fn parse_name(input: LocatedSpan) -> IResult<Ranged<Vec<&str>>> {
+fn parse_name(input: LocatedSpan<'_>) -> IResult<'_, Ranged<Vec<&str>>> {
     let parser = |input| {
         let (input, (_, context_range)) = get_range(char('['))(input)?;
         let (input, res) = separated_list0(char('|'), is_not("|]"))(input)?;
         let (input, _) = expect_context(
             char(']'),
+            codes::UNCLOSED_BRACKET,
             "Expected closing `]`",
             Ranged {
                 inner: "Expected due to `[` found here".to_string(),
@@ -490,17 +728,23 @@ where
     F: FnMut(LocatedSpan<'a>) -> IResult<T>,
 {
     move |input: LocatedSpan<'a>| {
-        let top_level = input.extra.state.top_level;
+        let ParserState {
+            top_level, depth, ..
+        } = input.extra.state;
         let mut input = input;
         input.extra.state.top_level = false;
-        let res = parser(input);
+        input.extra.state.depth = depth + 1;
+        // Each level of nesting uses a lot of stack, so make sure there is enough before descending
+        let res = stacker::maybe_grow(256 * 1024, 2 * 1024 * 1024, || parser(input));
         match res {
             Ok(mut it) => {
                 it.0.extra.state.top_level = top_level;
+                it.0.extra.state.depth = depth;
                 Ok(it)
             }
             Err(nom::Err::Error(mut err) | nom::Err::Failure(mut err)) => {
                 err.input.extra.state.top_level = top_level;
+                err.input.extra.state.depth = depth;
                 Err(nom::Err::Error(err))
             }
             Err(err) => Err(err),
@@ -508,6 +752,53 @@ where
     }
 }
 
+/// If the maximum nesting depth is reached, consumes the next node without parsing it, and reports an error
+fn nested_too_deep(input: LocatedSpan<'_>) -> IResult<'_, Ranged<&str>> {
+    let ParserState {
+        depth, max_depth, ..
+    } = input.extra.state;
+    let fail = |input| {
+        Err(nom::Err::Error(nom::error::Error {
+            input,
+            code: nom::error::ErrorKind::Fail,
+        }))
+    };
+    if depth < max_depth {
+        return fail(input);
+    }
+    // Find the end of the node by matching brackets. If there is no opening bracket before the block ends, it is not a node
+    let mut open = 0usize;
+    let mut end = None;
+    for (i, c) in input.fragment().char_indices() {
+        match c {
+            '{' => open += 1,
+            '}' if open == 0 => return fail(input),
+            '}' if open == 1 => {
+                end = Some(i + 1);
+                break;
+            }
+            '}' => open -= 1,
+            _ => (),
+        }
+    }
+    let end = match end {
+        Some(end) => end,
+        None if open > 0 => input.len(),
+        None => return fail(input),
+    };
+    // `end` is in bytes, while `take` counts chars
+    let (rest, skipped) = input.take_split(end);
+    rest.extra.report_error(Error {
+        severity: Severity::Error,
+        code: codes::NESTED_TOO_DEEP,
+        range: Range::from(skipped.clone()),
+        source: (*skipped.fragment()).to_string(),
+        message: format!("nodes are nested deeper than the maximum depth of {max_depth}"),
+        context: None,
+    });
+    Ok((rest, Ranged::from(skipped)))
+}
+
 fn parse_block(input: LocatedSpan) -> IResult<(Vec<NodeItem>, bool)> {
     log::trace!("parsing block:\n{input}");
     let block = delimited(
@@ -517,8 +808,12 @@ fn parse_block(input: LocatedSpan) -> IResult<(Vec<NodeItem>, bool)> {
                 map(ignore_line_ending(ws(Comment::parse)), |c| {
                     NodeItem::Comment(c)
                 }),
-                map(ws(empty_line), |()| NodeItem::EmptyLine),
+                map(range_wrap(ws(empty_line)), |empty| {
+                    NodeItem::EmptyLine(empty.get_range().to_start())
+                }),
+                map(ws(block_comment), NodeItem::Error),
                 debug_fn(map(ws(KeyVal::parse), NodeItem::KeyVal), "keyval", false),
+                map(ignore_line_ending(ws(nested_too_deep)), NodeItem::Error),
                 settings_for_inner_block(map(ignore_line_ending(ws(Node::parse)), NodeItem::Node)),
                 debug_fn(
                     map(
@@ -533,20 +828,47 @@ fn parse_block(input: LocatedSpan) -> IResult<(Vec<NodeItem>, bool)> {
             true,
         ))),
         debug_fn(
-            expect(char('}'), "Expected closing }"),
+            expect(char('}'), codes::UNCLOSED_BRACKET, "Expected closing }"),
             "closing bracket",
             true,
         ),
     );
-    map(block, |inner: (LocatedSpan, Vec<NodeItem>)| {
-        (inner.1, !inner.0.contains('\n'))
-    })(input)
+    map(
+        block,
+        |(consumed, mut items): (LocatedSpan, Vec<NodeItem>)| {
+            for item in &mut items {
+                if let NodeItem::Comment(comment) = item {
+                    comment.indentation = comment_indentation(&consumed, comment.get_range().start);
+                }
+            }
+            (items, !consumed.contains('\n'))
+        },
+    )(input)
+}
+
+/// The whitespace before a comment starting at `start`, if the comment is the first thing on its line in `block`
+fn comment_indentation<'a>(block: &LocatedSpan<'a>, start: Position) -> Option<&'a str> {
+    // The first line of the block starts with the `{`
+    let line_index = (start.line as usize).checked_sub(block.location_line() as usize)?;
+    if line_index == 0 {
+        return None;
+    }
+    let line = block.fragment().split('\n').nth(line_index)?;
+    let end = line
+        .char_indices()
+        .nth((start.col as usize).saturating_sub(1))
+        .map_or(line.len(), |(i, _)| i);
+    let indentation = &line[..end];
+    indentation
+        .chars()
+        .all(|c| c == ' ' || c == '\t')
+        .then_some(indentation)
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::parser::{LocatedSpan, State};
+    use crate::parser::{DocItem, LocatedSpan, Position, State};
 
     use super::*;
     #[test]
@@ -555,7 +877,10 @@ mod tests {
         let res = Node::parse(LocatedSpan::new_extra(input, State::default()));
 
         match res {
-            Ok(it) => assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", Some(true))),
+            Ok(it) => assert_eq!(
+                input,
+                it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(Some(true)))
+            ),
             Err(err) => panic!("{}", err),
         }
     }
@@ -565,8 +890,199 @@ mod tests {
         let res = Node::parse(LocatedSpan::new_extra(input, State::default()));
 
         match res {
-            Ok(it) => assert_eq!(input, it.1.ast_print(0, "\t", "\r\n", Some(true))),
+            Ok(it) => assert_eq!(
+                input,
+                it.1.ast_print(0, "\t", "\r\n", &PrintSettings::new(Some(true)))
+            ),
+            Err(err) => panic!("{:#?}", err),
+        }
+    }
+    #[test]
+    fn test_comment_inside_identifier() {
+        let input = "@PART //x\r\n[foo]\r\n{\r\n\tkey = val\r\n}\r\n";
+        let res = Node::parse(LocatedSpan::new_extra(input, State::default()));
+
+        match res {
+            Ok(it) => {
+                let errors = it.0.extra.errors.into_inner();
+                assert_eq!(errors.len(), 1);
+                assert_eq!(
+                    errors[0].message,
+                    "comments are not allowed between the parts of a node identifier, move it after the identifier"
+                );
+                assert_eq!(errors[0].range.start, crate::parser::Position::new(2, 1));
+            }
             Err(err) => panic!("{:#?}", err),
         }
     }
+
+    #[test]
+    fn test_max_depth() {
+        let input = "node {".repeat(5000) + &"}".repeat(5000);
+        let (_doc, errors) = crate::parser::parse(&input);
+        assert!(errors
+            .iter()
+            .any(|e| e.message.contains("maximum depth of 256")));
+
+        let input = "a\n{\n\tb\n\t{\n\t\tc\n\t\t{\n\t\t}\n\t}\n}\n";
+        let (_doc, errors) = crate::parser::parse_with_max_depth(input, 2);
+        assert!(errors.is_empty(), "{errors:#?}");
+        let (_doc, errors) = crate::parser::parse_with_max_depth(input, 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].range.start, Position::new(5, 3));
+        assert_eq!(errors[0].source, "c\n\t\t{\n\t\t}");
+
+        // The end of the skipped node is found by bytes, so non-ASCII values don't move it
+        for value in ["ééééééé".to_owned(), "é".repeat(28)] {
+            let input = format!("a\n{{\n\tb\n\t{{\n\t\tc {{ k = {value} }}\n\t\td = 2\n\t}}\n}}\n");
+            let (_doc, errors) = crate::parser::parse_with_max_depth(&input, 1);
+            assert_eq!(errors.len(), 1, "{errors:#?}");
+            assert_eq!(errors[0].source, format!("c {{ k = {value} }}"));
+        }
+    }
+
+    #[test]
+    fn test_keyvals_named() {
+        let input =
+            "PART\n{\n\tmodule = a\n\tname = foo\n\t@module = b\n\tMODULE { module = c }\n}\n";
+        let (doc, errors) = crate::parser::parse(input);
+        assert!(errors.is_empty(), "{errors:#?}");
+        let DocItem::Node(node) = &doc.statements[0] else {
+            panic!("expected a node")
+        };
+        let values: Vec<&str> = node.keyvals_named("module").map(|kv| &**kv.val).collect();
+        assert_eq!(values, vec!["a", "b"]);
+        assert_eq!(node.first_keyval("module").map(|kv| &**kv.val), Some("a"));
+        assert_eq!(node.first_keyval("name").map(|kv| &**kv.val), Some("foo"));
+        assert!(node.first_keyval("Module").is_none());
+    }
+
+    #[test]
+    fn test_patch_kind() {
+        let input = "PART {}\n@PART {}\n%PART {}\n!PART {}\n-PART {}\n+PART {}\n@PART\n{\n\t#../PART/CHILD {}\n}\n";
+        let (doc, errors) = crate::parser::parse(input);
+        assert!(errors.is_empty(), "{errors:#?}");
+        let nodes = doc
+            .statements
+            .iter()
+            .filter_map(|item| match item {
+                crate::parser::DocItem::Node(node) => Some(node),
+                _ => None,
+            })
+            .collect_vec();
+        let kinds = |node: &Node| {
+            (
+                node.is_patch(),
+                node.is_insert(),
+                node.is_delete(),
+                node.is_edit(),
+            )
+        };
+        assert_eq!(kinds(nodes[0]), (false, true, false, false));
+        assert_eq!(kinds(nodes[1]), (true, false, false, true));
+        assert_eq!(kinds(nodes[2]), (true, false, false, true));
+        assert_eq!(kinds(nodes[3]), (true, false, true, false));
+        assert_eq!(kinds(nodes[4]), (true, false, true, false));
+        assert_eq!(kinds(nodes[5]), (true, false, false, false));
+        // A node with a path, but no operator, is not an insert
+        let child = nodes[6].iter_nodes().next().unwrap();
+        assert_eq!(kinds(child), (true, false, false, false));
+    }
+
+    #[test]
+    fn test_eq_ignore_ranges() {
+        let parse_node = |input| {
+            let (doc, errors) = crate::parser::parse(input);
+            assert!(errors.is_empty(), "{errors:#?}");
+            let DocItem::Node(node) = doc.statements[0].clone() else {
+                panic!("expected a node in {input:?}")
+            };
+            node
+        };
+        let a =
+            parse_node("@PART[foo]:NEEDS[mod] // comment\n{\n\tkey = val\n\tchild { a = b }\n}\n");
+        let b = parse_node(
+            "@PART[foo]:NEEDS[mod] // comment\n{\n  key   =   val\n  child\n  {\n    a = b\n  }\n}\n",
+        );
+        assert_ne!(a, b);
+        assert!(a.eq_ignore_ranges(&b));
+        let NodeItem::KeyVal(key_a) = &a.block[0] else {
+            panic!()
+        };
+        let NodeItem::KeyVal(key_b) = &b.block[0] else {
+            panic!()
+        };
+        assert!(key_a.eq_ignore_ranges(key_b));
+
+        let c = parse_node(
+            "@PART[foo]:NEEDS[mod] // comment\n{\n\tkey = other\n\tchild { a = b }\n}\n",
+        );
+        assert!(!a.eq_ignore_ranges(&c));
+    }
+
+    #[test]
+    fn test_identifier_spacing() {
+        for (input, expected) in [
+            ("@ PART { }\n", "@PART {}\n"),
+            ("PART  { }\n", "PART {}\n"),
+            ("@\tPART[foo]  \n{\n}\n", "@PART[foo] {}\n"),
+        ] {
+            let (doc, errors) = crate::parser::parse(input);
+            assert!(errors.is_empty(), "{input:?}: {errors:#?}");
+            let DocItem::Node(node) = &doc.statements[0] else {
+                panic!("expected a node in {input:?}")
+            };
+            assert_eq!(*node.identifier, "PART");
+            assert_eq!(
+                node.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_multiple_passes() {
+        let (_doc, errors) = crate::parser::parse("@PART:FIRST:FINAL { }\n");
+        assert_eq!(errors.len(), 1, "{errors:#?}");
+        assert_eq!(errors[0].severity, Severity::Error);
+        assert_eq!(
+            errors[0].message,
+            "a patch can only run in one pass, but `:FINAL` is given in addition to `:FIRST`"
+        );
+        assert_eq!(errors[0].range, Range::new(1, 12, 1, 18));
+        assert_eq!(
+            errors[0].context.as_ref().map(Ranged::get_range),
+            Some(Range::new(1, 6, 1, 12))
+        );
+
+        let (_doc, errors) = crate::parser::parse("@PART:FOR[a]:NEEDS[b]:AFTER[c] { }\n");
+        assert_eq!(errors.len(), 1, "{errors:#?}");
+        assert!(errors[0].message.contains("`:AFTER[c]`"));
+    }
+
+    #[test]
+    fn test_clause_order() {
+        let clauses = [":HAS[#key]", ":FOR[mod]", ":NEEDS[other]"];
+        let expected = "@PART[foo]:HAS[#key]:FOR[mod]:NEEDS[other],0 {}\n";
+        let (canonical, _) = crate::parser::parse(expected);
+        for order in [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ] {
+            let header = order.iter().map(|&i| clauses[i]).collect::<String>();
+            let text = format!("@PART[foo]{header},0 {{}}\n");
+            let (doc, errors) = crate::parser::parse(&text);
+            assert!(errors.is_empty(), "{text}: {errors:#?}");
+            assert!(doc.eq_ignore_ranges(&canonical), "{text}");
+            assert_eq!(
+                doc.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+                expected,
+                "{text}"
+            );
+        }
+    }
 }
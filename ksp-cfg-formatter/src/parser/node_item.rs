@@ -1,7 +1,9 @@
-use super::{ASTPrint, Comment, KeyVal, Node, Ranged};
+use super::{ASTPrint, Comment, DocItem, KeyVal, Node, PrintSettings, Range, Ranged};
 
 /// Enum for the different items that can exist in a node
-#[derive(Debug, Clone)]
+///
+/// Comments are printed at the depth of the block, unless [`PrintSettings::keep_comment_indentation`] is set, which keeps the whitespace before them as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum NodeItem<'a> {
     /// A node
     Node(Ranged<Node<'a>>),
@@ -9,29 +11,146 @@ pub enum NodeItem<'a> {
     Comment(Ranged<Comment<'a>>),
     /// A key-value pair
     KeyVal(Ranged<KeyVal<'a>>),
-    /// An empty line
-    EmptyLine,
+    /// An empty line, with an empty range at the start of the line
+    EmptyLine(Range),
     /// An error instead of the node item
     Error(Ranged<&'a str>),
 }
+
+impl<'a> NodeItem<'a> {
+    /// Returns the range covered by the item. An `EmptyLine` returns an empty range at the start of the line
+    #[must_use]
+    pub fn range(&self) -> Range {
+        match self {
+            Self::Node(node) => node.get_range(),
+            Self::Comment(comment) => comment.get_range(),
+            Self::KeyVal(key_val) => key_val.get_range(),
+            Self::EmptyLine(range) => *range,
+            Self::Error(error) => error.get_range(),
+        }
+    }
+    /// Compares two items, ignoring the ranges. See [`Node::eq_ignore_ranges()`]
+    #[must_use]
+    pub fn eq_ignore_ranges(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Node(a), Self::Node(b)) => a.eq_ignore_ranges(b),
+            (Self::Comment(a), Self::Comment(b)) => a.text == b.text,
+            (Self::KeyVal(a), Self::KeyVal(b)) => a.eq_ignore_ranges(b),
+            (Self::EmptyLine(_), Self::EmptyLine(_)) => true,
+            (Self::Error(a), Self::Error(b)) => **a == **b,
+            _ => false,
+        }
+    }
+}
 impl<'a> ASTPrint for NodeItem<'a> {
     fn ast_print(
         &self,
         depth: usize,
         indentation: &str,
         line_ending: &str,
-        should_collapse: Option<bool>,
+        settings: &PrintSettings,
     ) -> String {
         match self {
-            Self::Node(node) => node.ast_print(depth, indentation, line_ending, should_collapse),
-            Self::Comment(comment) => {
-                comment.ast_print(depth, indentation, line_ending, should_collapse)
-            }
-            Self::KeyVal(keyval) => {
-                keyval.ast_print(depth, indentation, line_ending, should_collapse)
+            Self::Node(node) => node.ast_print(depth, indentation, line_ending, settings),
+            Self::Comment(comment) if settings.keep_comment_indentation => {
+                match comment.indentation {
+                    // The comment is kept as written, like a section divider at the start of the line
+                    Some(leading) => format!(
+                        "{leading}{}",
+                        comment.ast_print(0, indentation, line_ending, settings)
+                    ),
+                    None => comment.ast_print(depth, indentation, line_ending, settings),
+                }
             }
-            Self::EmptyLine => line_ending.to_owned(),
+            Self::Comment(comment) => comment.ast_print(depth, indentation, line_ending, settings),
+            Self::KeyVal(keyval) => keyval.ast_print(depth, indentation, line_ending, settings),
+            Self::EmptyLine(_) => line_ending.to_owned(),
             Self::Error(e) => e.to_string(),
         }
     }
 }
+
+/// Every item in a document can also be in a node. The node keeps its [`Node::top_level()`] flag, so a node moved from a document into a block is still linted as a top level node
+impl<'a> From<DocItem<'a>> for NodeItem<'a> {
+    fn from(value: DocItem<'a>) -> Self {
+        match value {
+            DocItem::Node(node) => Self::Node(node),
+            DocItem::Comment(comment) => Self::Comment(comment),
+            DocItem::EmptyLine(range) => Self::EmptyLine(range),
+            DocItem::Error(error) => Self::Error(error),
+        }
+    }
+}
+
+/// Assignments are only allowed inside nodes, so converting a `KeyVal` fails, returning the assignment. The node keeps its [`Node::top_level()`] flag, like for the opposite conversion
+impl<'a> TryFrom<NodeItem<'a>> for DocItem<'a> {
+    type Error = Ranged<KeyVal<'a>>;
+
+    fn try_from(value: NodeItem<'a>) -> Result<Self, Ranged<KeyVal<'a>>> {
+        match value {
+            NodeItem::Node(node) => Ok(Self::Node(node)),
+            NodeItem::Comment(comment) => Ok(Self::Comment(comment)),
+            NodeItem::KeyVal(key_val) => Err(key_val),
+            NodeItem::EmptyLine(range) => Ok(Self::EmptyLine(range)),
+            NodeItem::Error(error) => Ok(Self::Error(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, DocItem, NodeItem, Range};
+
+    #[test]
+    fn test_node_item_ranges() {
+        let input = "node\n{\n\t// c\n\tkey = val\n\n\tchild {}\n\tgarbage\n}\n";
+        let (doc, _errors) = parse(input);
+        let DocItem::Node(node) = &doc.statements[0] else {
+            panic!("expected a node, got {:#?}", doc.statements[0]);
+        };
+        let ranges: Vec<Range> = node.block.iter().map(super::NodeItem::range).collect();
+        assert_eq!(
+            ranges,
+            vec![
+                // Comment
+                Range::new(3, 2, 3, 6),
+                // KeyVal, including the line ending
+                Range::new(4, 2, 5, 1),
+                // Empty line
+                Range::new(5, 1, 5, 1),
+                // Node
+                Range::new(6, 2, 6, 10),
+                // Error
+                Range::new(7, 2, 7, 9),
+                // Line ending after the error
+                Range::new(7, 9, 7, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_item_conversion() {
+        let (doc, errors) = parse("// c\n\nnode\n{\n\tkey = val\n}\ngarbage\n");
+        assert_eq!(errors.len(), 1);
+        let DocItem::Node(node) = &doc.statements[2] else {
+            panic!("expected a node, got {:#?}", doc.statements[2]);
+        };
+        let NodeItem::KeyVal(key_val) = node.block[0].clone() else {
+            panic!("expected an assignment, got {:#?}", node.block[0]);
+        };
+        // Comment, empty line, node and error
+        for item in doc.statements.iter().take(4) {
+            let node_item = NodeItem::from(item.clone());
+            assert_eq!(node_item.range(), item.range());
+            assert_eq!(DocItem::try_from(node_item).as_ref(), Ok(item));
+        }
+        assert!(matches!(
+            NodeItem::from(doc.statements[2].clone()),
+            NodeItem::Node(node) if node.top_level()
+        ));
+        assert_eq!(
+            DocItem::try_from(NodeItem::KeyVal(key_val.clone())),
+            Err(key_val)
+        );
+    }
+}
@@ -1,10 +1,32 @@
 use nom::{branch::alt, character::complete::char, combinator::value};
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-use super::{parser_helpers::range_wrap, ASTParse, Ranged};
+use super::{parse_complete, parser_helpers::range_wrap, ASTParse, Error, Ranged};
 
 /// The different kinds of operations that can be done
-#[derive(Debug, Clone, Default, Copy)]
+///
+/// | Char | Variant              | Meaning                                      |
+/// |------|----------------------|----------------------------------------------|
+/// |      | [`None`]             | No operation, define a new node/variable     |
+/// | `@`  | [`Edit`]             | Edit an existing node/variable               |
+/// | `%`  | [`EditOrCreate`]     | Edit a node/variable, creating it if missing |
+/// | `&`  | [`CreateIfNotFound`] | Create a node/variable only if missing       |
+/// | `+`  | [`Copy`]             | Copy an existing node/variable               |
+/// | `$`  | [`CopyAlt`]          | Copy an existing node/variable               |
+/// | `!`  | [`Delete`]           | Delete a node/variable                       |
+/// | `-`  | [`DeleteAlt`]        | Delete a node/variable                       |
+/// | `\|` | [`Rename`]           | Rename a node                                |
+///
+/// [`None`]: Operator::None
+/// [`Edit`]: Operator::Edit
+/// [`EditOrCreate`]: Operator::EditOrCreate
+/// [`CreateIfNotFound`]: Operator::CreateIfNotFound
+/// [`Copy`]: Operator::Copy
+/// [`CopyAlt`]: Operator::CopyAlt
+/// [`Delete`]: Operator::Delete
+/// [`DeleteAlt`]: Operator::DeleteAlt
+/// [`Rename`]: Operator::Rename
+#[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
 pub enum Operator {
     /// No operator. This is never parsed, only used as a default fallback for printing
     #[default]
@@ -17,6 +39,8 @@ pub enum Operator {
     CreateIfNotFound,
     /// Copy an existing node/variable
     Copy,
+    /// Copy an existing node/variable (Alt 2)
+    CopyAlt,
     /// Delete a node/variable
     Delete,
     /// Delete a node/variable (Alt 2)
@@ -32,6 +56,7 @@ impl Display for Operator {
             Operator::Edit => write!(f, "@"),
             Operator::EditOrCreate => write!(f, "%"),
             Operator::Copy => write!(f, "+"),
+            Operator::CopyAlt => write!(f, "$"),
             Operator::Delete => write!(f, "!"),
             Operator::DeleteAlt => write!(f, "-"),
             Operator::CreateIfNotFound => write!(f, "&"),
@@ -40,12 +65,24 @@ impl Display for Operator {
     }
 }
 
+impl FromStr for Operator {
+    type Err = Error;
+
+    /// Parses an operator from its character, e.g. `"@".parse::<Operator>()`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_complete::<Operator>(s, "operator")
+            .map(|op| *op)
+            .map_err(|mut errors| errors.remove(0))
+    }
+}
+
 impl ASTParse<'_> for Operator {
     fn parse(input: super::LocatedSpan) -> super::IResult<Ranged<Operator>> {
         let operator = alt((
             value(Operator::Edit, char('@')),
             value(Operator::EditOrCreate, char('%')),
             value(Operator::Copy, char('+')),
+            value(Operator::CopyAlt, char('$')),
             value(Operator::Delete, char('!')),
             value(Operator::DeleteAlt, char('-')),
             value(Operator::CreateIfNotFound, char('&')),
@@ -54,3 +91,87 @@ impl ASTParse<'_> for Operator {
         range_wrap(operator)(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let operators = [
+            ("@", Operator::Edit),
+            ("%", Operator::EditOrCreate),
+            ("+", Operator::Copy),
+            ("$", Operator::CopyAlt),
+            ("!", Operator::Delete),
+            ("-", Operator::DeleteAlt),
+            ("&", Operator::CreateIfNotFound),
+            ("|", Operator::Rename),
+        ];
+        for (input, expected) in operators {
+            assert_eq!(input.parse::<Operator>(), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_from_str_error() {
+        assert_eq!(
+            "?".parse::<Operator>().unwrap_err().message,
+            "expected operator"
+        );
+        assert_eq!(
+            "@@".parse::<Operator>().unwrap_err().message,
+            "unexpected `@` after operator"
+        );
+        assert!("".parse::<Operator>().is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        use crate::parser::{parse, ASTPrint, PrintSettings};
+
+        for operator in ["@", "%", "+", "$", "!", "-", "&"] {
+            let input =
+                format!("{operator}NODE\n{{\n\t{operator}key = val\n\t{operator}CHILD {{}}\n}}\n");
+            let (doc, errors) = parse(&input);
+            assert!(errors.is_empty(), "{errors:#?}");
+            assert_eq!(
+                doc.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+                input
+            );
+        }
+        // Rename is only allowed on child nodes
+        let input = "@NODE\n{\n\t|CHILD {}\n}\n";
+        let (doc, errors) = parse(input);
+        assert!(errors.is_empty(), "{errors:#?}");
+        assert_eq!(
+            doc.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+            input
+        );
+    }
+
+    #[test]
+    fn test_create_if_not_found() {
+        use crate::parser::{parse, DocItem, NodeItem};
+
+        let (doc, errors) = parse("&NODE\n{\n\t&key = val\n\t&CHILD {}\n}\n");
+        assert!(errors.is_empty(), "{errors:#?}");
+        let Some(DocItem::Node(node)) = doc.statements.first() else {
+            panic!("expected a node");
+        };
+        assert_eq!(node.operator.as_deref(), Some(&Operator::CreateIfNotFound));
+        let operators: Vec<_> = node
+            .block
+            .iter()
+            .filter_map(|item| match item {
+                NodeItem::KeyVal(key_val) => key_val.operator.as_deref(),
+                NodeItem::Node(child) => child.operator.as_deref(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            operators,
+            [&Operator::CreateIfNotFound, &Operator::CreateIfNotFound]
+        );
+    }
+}
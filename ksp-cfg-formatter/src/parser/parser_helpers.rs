@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 use log::debug;
 use nom::{
@@ -11,7 +11,7 @@ use nom::{
 
 use crate::parser::{Position, Range, Ranged};
 
-use crate::parser::{Error, IResult, LocatedSpan};
+use crate::parser::{codes, Error, IResult, LocatedSpan};
 
 pub(crate) fn ignore_line_ending<'a, F, T>(parser: F) -> impl FnMut(LocatedSpan<'a>) -> IResult<T>
 where
@@ -20,6 +20,11 @@ where
     terminated(parser, opt(line_ending))
 }
 
+/// Compares two optional items by their printed form. Used to compare items ignoring the ranges nested inside them
+pub(crate) fn printed_eq<T: Display>(a: &Option<Ranged<T>>, b: &Option<Ranged<T>>) -> bool {
+    a.as_ref().map(ToString::to_string) == b.as_ref().map(ToString::to_string)
+}
+
 pub(crate) fn empty_line(input: LocatedSpan) -> IResult<()> {
     let empty_line = recognize(pair(space0, line_ending));
     map(empty_line, |_| ())(input)
@@ -40,6 +45,7 @@ where
 /// parsing to continue.
 pub(crate) fn expect<'a, F, E, T>(
     mut parser: F,
+    code: &'static str,
     error_msg: E,
 ) -> impl FnMut(LocatedSpan<'a>) -> IResult<Option<T>>
 where
@@ -53,6 +59,7 @@ where
                 let input = error.input;
                 let length = usize::from(!input.is_empty());
                 let err = Error {
+                    code,
                     source: (*input.fragment()).to_string(),
                     range: Range::from(input.slice(0..length)),
                     message: error_msg.to_string(),
@@ -72,6 +79,7 @@ where
 /// parsing to continue.
 pub(crate) fn expect_context<'a, F, E, T>(
     mut parser: F,
+    code: &'static str,
     error_msg: E,
     context_msg: Ranged<String>,
 ) -> impl FnMut(LocatedSpan<'a>) -> IResult<Option<T>>
@@ -87,6 +95,7 @@ where
                 let input = error.input;
                 let length = usize::from(!input.is_empty());
                 let err = Error {
+                    code,
                     source: (*input.fragment()).to_string(),
                     range: Range::from(input.slice(0..length)),
                     message: error_msg.to_string(),
@@ -134,6 +143,28 @@ where
     }
 }
 
+/// Message for unexpected text, with a hint for common mistakes
+fn unexpected_message(span: &LocatedSpan) -> String {
+    let text = span.fragment();
+    let trimmed = text.trim();
+    // The text before the unexpected text on the same line
+    let line_start = &span.get_line_beginning()[..span.get_column() - 1];
+    if trimmed.len() == 1 && "@%+$!-&|".contains(trimmed) {
+        format!("unexpected `{text}`. Expected a node or key after the operator")
+    } else if trimmed.starts_with('{') {
+        format!("unexpected `{text}`. Expected a node name before the opening bracket")
+    } else if trimmed == "}" {
+        format!("unexpected `{text}`. There is no node to close")
+    } else if line_start.trim_ascii_end().ends_with(b"}") {
+        format!("unexpected `{text}` after the closing bracket. Only a comment can follow `}}` on the same line")
+    } else if crate::parser::parse_key_val(trimmed).is_ok() {
+        // Assignments are only parsed inside nodes, so this is a top level assignment
+        "assignments must be inside a node".to_owned()
+    } else {
+        format!("unexpected `{text}`")
+    }
+}
+
 pub(crate) fn error_till<'a, F>(mut parser: F) -> impl FnMut(LocatedSpan<'a>) -> IResult<'a, ()>
 where
     F: FnMut(LocatedSpan<'a>) -> IResult<LocatedSpan>,
@@ -142,8 +173,9 @@ where
         Ok((rem, out)) => {
             if out.len() > 0 {
                 rem.extra.report_error(Error {
+                    code: codes::UNEXPECTED_TEXT,
                     source: (*out.fragment()).to_string(),
-                    message: format!("unexpected `{}`", out.fragment()),
+                    message: unexpected_message(&out),
                     range: Range::from(out),
                     severity: crate::parser::Severity::Error,
                     context: None,
@@ -2,17 +2,18 @@ use std::fmt::Display;
 
 use nom::{
     branch::alt,
-    bytes::complete::{is_a, tag_no_case},
+    bytes::complete::{is_a, tag, tag_no_case},
     character::complete::char,
     combinator::{map, recognize},
     multi::many1,
-    sequence::delimited,
+    sequence::{delimited, preceded},
 };
 use nom_unicode::complete::alphanumeric1;
 
 use super::{
-    parser_helpers::{expect, range_wrap},
-    Ranged, {ASTParse, IResult, LocatedSpan},
+    codes, parse_complete,
+    parser_helpers::{expect, get_range, range_wrap},
+    Error, Ranged, Severity, {ASTParse, IResult, LocatedSpan},
 };
 
 /// Which pass a patch should run on
@@ -49,47 +50,125 @@ impl<'a> Display for Pass<'a> {
     }
 }
 
+/// Parses a pass, e.g. `Pass::try_from(":FOR[Mod]")`.
+///
+/// `FromStr` can't be implemented, since the mod name of the pass borrows from the input
+impl<'a> TryFrom<&'a str> for Pass<'a> {
+    type Error = Error;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        parse_complete::<Pass>(value, "pass")
+            .map(|pass| *pass)
+            .map_err(|mut errors| errors.remove(0))
+    }
+}
+
 impl<'a> ASTParse<'a> for Pass<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<Pass<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<Pass<'a>>> {
         range_wrap(alt((
             map(tag_no_case(":FIRST"), |_| Pass::First),
             map(
-                delimited(
-                    tag_no_case(":BEFORE["),
-                    expect(pass_name, "Expected pass identifier"),
-                    expect(char(']'), "Expected closing `]`"),
-                ),
-                |inner| Pass::Before(inner.map_or("", |s| s.fragment())),
-            ),
-            map(
-                delimited(
-                    tag_no_case(":FOR["),
-                    expect(pass_name, "Expected pass identifier"),
-                    expect(char(']'), "Expected closing `]`"),
-                ),
-                |inner| Pass::For(inner.map_or("", |s| s.fragment())),
-            ),
-            map(
-                delimited(
-                    tag_no_case(":AFTER["),
-                    expect(pass_name, "Expected pass identifier"),
-                    expect(char(']'), "Expected closing `]`"),
-                ),
-                |inner| Pass::After(inner.map_or("", |s| s.fragment())),
-            ),
-            map(
-                delimited(
-                    tag_no_case(":LAST["),
-                    expect(pass_name, "Expected pass identifier"),
-                    expect(char(']'), "Expected closing `]`"),
-                ),
-                |inner| Pass::Last(inner.map_or("", |s| s.fragment())),
+                preceded(tag_no_case(":BEFORE"), pass_argument),
+                Pass::Before,
             ),
+            map(preceded(tag_no_case(":FOR"), pass_argument), Pass::For),
+            map(preceded(tag_no_case(":AFTER"), pass_argument), Pass::After),
+            map(preceded(tag_no_case(":LAST"), pass_argument), Pass::Last),
             map(tag_no_case(":FINAL"), |_| Pass::Final),
         )))(input)
     }
 }
 
+/// The bracketed mod name of a pass, `[MyMod]`. An empty name, `[]`, is reported as an error
+fn pass_argument(input: LocatedSpan<'_>) -> IResult<'_, &str> {
+    if let Ok((rest, (brackets, range))) =
+        get_range(tag::<_, _, nom::error::Error<_>>("[]"))(input.clone())
+    {
+        rest.extra.report_error(Error {
+            severity: Severity::Error,
+            code: codes::EXPECTED_TOKEN,
+            range,
+            source: (*brackets.fragment()).to_owned(),
+            message: "the pass is missing the name of a mod, e.g. `:FOR[MyMod]`".to_owned(),
+            context: None,
+        });
+        return Ok((rest, ""));
+    }
+    map(
+        delimited(
+            char('['),
+            expect(pass_name, codes::EXPECTED_TOKEN, "Expected pass identifier"),
+            expect(char(']'), codes::UNCLOSED_BRACKET, "Expected closing `]`"),
+        ),
+        |inner| inner.map_or("", |s| *s.fragment()),
+    )(input)
+}
+
 fn pass_name(input: LocatedSpan) -> IResult<LocatedSpan> {
     recognize(many1(alt((alphanumeric1::<LocatedSpan, _>, is_a("/_-?")))))(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_from() {
+        let passes = [
+            (":FIRST", Pass::First),
+            (":BEFORE[Mod]", Pass::Before("Mod")),
+            (":FOR[Mod]", Pass::For("Mod")),
+            (":AFTER[Mod]", Pass::After("Mod")),
+            (":LAST[Mod]", Pass::Last("Mod")),
+            (":FINAL", Pass::Final),
+        ];
+        for (input, expected) in passes {
+            assert_eq!(Pass::try_from(input), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_try_from_error() {
+        assert_eq!(
+            Pass::try_from(":SECOND").unwrap_err().message,
+            "expected pass"
+        );
+        assert_eq!(
+            Pass::try_from(":FOR[Mod").unwrap_err().message,
+            "Expected closing `]`"
+        );
+        assert_eq!(
+            Pass::try_from(":FIRST:FINAL").unwrap_err().message,
+            "unexpected `:FINAL` after pass"
+        );
+    }
+
+    #[test]
+    fn test_empty_pass_name() {
+        for input in ["@PART:FOR[] {}\n", "@PART:BEFORE[]:NEEDS[mod] {}\n"] {
+            let (doc, errors) = crate::parser::parse(input);
+            assert_eq!(errors.len(), 1, "{errors:#?}");
+            assert_eq!(errors[0].severity, Severity::Error);
+            assert_eq!(
+                errors[0].message,
+                "the pass is missing the name of a mod, e.g. `:FOR[MyMod]`"
+            );
+            let start = input.find('[').unwrap() as u32 + 1;
+            assert_eq!(
+                errors[0].range,
+                crate::parser::Range::new(1, start, 1, start + 2)
+            );
+            // The text is kept as is
+            assert_eq!(
+                crate::parser::ASTPrint::ast_print(
+                    &doc,
+                    0,
+                    "\t",
+                    "\n",
+                    &crate::parser::PrintSettings::new(None)
+                ),
+                input
+            );
+        }
+    }
+}
@@ -12,8 +12,9 @@ use nom::{
 use nom_unicode::complete::alphanumeric1;
 
 use super::{
+    codes,
     parser_helpers::{debug_fn, expect, range_wrap},
-    Ranged, {ASTParse, IResult, LocatedSpan},
+    HasBlock, Node, NodeItem, Ranged, {ASTParse, IResult, LocatedSpan},
 };
 
 /// Where the path starts from
@@ -42,12 +43,13 @@ pub enum PathSegment<'a> {
     /// Segment is `..`, going up a level
     DotDot,
     /// Name of a node to traverse into
-    // TODO: :HAS[] is allowed
     NodeName {
         /// Node type
         node: &'a str,
         /// Optional node name
         name: Option<&'a str>,
+        /// Optional HAS block, filtering which node to traverse into
+        has: Option<Ranged<HasBlock<'a>>>,
         /// Optional index of the node
         index: Option<i32>,
     },
@@ -57,17 +59,49 @@ impl<'a> Display for PathSegment<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             PathSegment::DotDot => write!(f, "../"),
-            PathSegment::NodeName { node, name, index } => write!(
+            PathSegment::NodeName {
+                node,
+                name,
+                has,
+                index,
+            } => write!(
                 f,
-                "{}{}{}/",
+                "{}{}{}{}/",
                 node,
                 name.map_or_else(String::new, |name| format!("[{name}]")),
+                has.clone().unwrap_or_default(),
                 index.map_or_else(String::new, |index| index.to_string())
             ),
         }
     }
 }
 
+impl<'a> PathSegment<'a> {
+    /// Checks if the node is matched by the segment, comparing the node type and name.
+    /// The name is taken from the brackets after the node type, or from the `name` assignment in the node.
+    ///
+    /// `..` never matches, and the HAS block and index are not checked
+    #[must_use]
+    pub fn matches(&self, node: &Node) -> bool {
+        match self {
+            PathSegment::DotDot => false,
+            PathSegment::NodeName {
+                node: node_type,
+                name,
+                ..
+            } => {
+                *node.identifier == *node_type
+                    && name.is_none_or(|name| match &node.name {
+                        Some(names) => names.contains(&name),
+                        None => node.block.iter().any(|item| {
+                            matches!(item, NodeItem::KeyVal(kv) if *kv.key == "name" && *kv.val == name)
+                        }),
+                    })
+            }
+        }
+    }
+}
+
 /// A path to a node or a variable
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Path<'a> {
@@ -91,7 +125,7 @@ impl<'a> Display for Path<'a> {
 }
 
 impl<'a> ASTParse<'a> for Path<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<Path<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<Path<'a>>> {
         let start = opt(PathStart::parse);
         let segments = many0(PathSegment::parse);
         let path = pair(
@@ -106,7 +140,7 @@ impl<'a> ASTParse<'a> for Path<'a> {
 }
 
 impl ASTParse<'_> for PathStart {
-    fn parse(input: LocatedSpan<'_>) -> IResult<Ranged<PathStart>> {
+    fn parse(input: LocatedSpan<'_>) -> IResult<'_, Ranged<PathStart>> {
         range_wrap(alt((
             value(PathStart::TopLevel, char('@')),
             value(PathStart::CurrentTopLevel, char('/')),
@@ -115,7 +149,7 @@ impl ASTParse<'_> for PathStart {
 }
 
 impl<'a> ASTParse<'a> for PathSegment<'a> {
-    fn parse(input: LocatedSpan<'a>) -> IResult<Ranged<PathSegment<'a>>> {
+    fn parse(input: LocatedSpan<'a>) -> IResult<'a, Ranged<PathSegment<'a>>> {
         let node = recognize(many1(alt((
             alphanumeric1::<LocatedSpan, _>,
             is_a("-_.+*?"),
@@ -123,13 +157,14 @@ impl<'a> ASTParse<'a> for PathSegment<'a> {
         let name = opt(delimited(
             char('['),
             recognize(is_not("]\r\n")),
-            expect(char(']'), "Expected closing `]`"),
+            expect(char(']'), codes::UNCLOSED_BRACKET, "Expected closing `]`"),
         ));
-        let segment = tuple((node, name));
+        let segment = tuple((node, name, opt(HasBlock::parse)));
         let dot_dot = map(tag(".."), |_| PathSegment::DotDot);
         let node_name = map(segment, |inner| PathSegment::NodeName {
             node: inner.0.fragment(),
             name: inner.1.map(|s| *s.fragment()),
+            has: inner.2,
             // TODO: Add index support
             index: None,
         });
@@ -153,4 +188,22 @@ mod tests {
             Err(err) => panic!("{}", err),
         }
     }
+
+    #[test]
+    fn test_path_has() {
+        let input = "@PART[foo]:HAS[#x[1]]/MODULE:HAS[@INNER]/";
+        let res = Path::parse(LocatedSpan::new_extra(input, State::default()));
+
+        match res {
+            Ok(it) => {
+                assert!(it.0.is_empty());
+                assert!(matches!(
+                    it.1.segments[0].as_ref(),
+                    PathSegment::NodeName { has: Some(_), .. }
+                ));
+                assert_eq!(input, it.1.to_string());
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
 }
@@ -0,0 +1,128 @@
+use nom::Slice;
+
+use super::{ASTParse, Error, LocatedSpan, Node, Position, Ranged, State};
+
+/// Parses only the top level node containing `position`, instead of the whole document.
+///
+/// The node is located by matching brackets, and the returned ranges are relative to the start of `text`,
+/// so they can be used together with the ranges from a previous parse of the whole document.
+/// Returns `None` if the position is not inside a top level node, or the node failed to parse.
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::parser::{reparse_node_at, Position};
+///
+/// let text = "first {}\nsecond\n{\n\tkey = val\n}\n";
+/// let (node, errors) = reparse_node_at(text, Position::new(4, 3)).unwrap();
+/// assert_eq!(*node.identifier, "second");
+/// assert_eq!(node.get_range().start, Position::new(2, 1));
+/// assert!(errors.is_empty());
+/// ```
+pub fn reparse_node_at(text: &str, position: Position) -> Option<(Ranged<Node<'_>>, Vec<Error>)> {
+    let offset = offset_of_position(text, position)?;
+    let (start, end) = top_level_node_spans(text)
+        .into_iter()
+        .find(|(start, end)| (*start..=*end).contains(&offset))?;
+    let input = LocatedSpan::new_extra(text, State::default()).slice(start..end);
+    let (rest, node) = Node::parse(input).ok()?;
+    let errors = rest.extra.errors.borrow().clone();
+    Some((node, errors))
+}
+
+/// Byte offset in `text` of the 1-indexed `position`
+fn offset_of_position(text: &str, position: Position) -> Option<usize> {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(position.line.checked_sub(1)? as usize)
+        .map(str::len)
+        .sum();
+    let line = text[line_start..].split('\n').next()?;
+    let col_offset = match line
+        .char_indices()
+        .nth(position.col.checked_sub(1)? as usize)
+    {
+        Some((i, _)) => i,
+        None => line.len(),
+    };
+    Some(line_start + col_offset)
+}
+
+/// Finds the start and end byte offsets of all top level nodes, by matching the brackets.
+///
+/// A trailing comment after the closing bracket is included in the node
+fn top_level_node_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = vec![];
+    let mut depth = 0usize;
+    let mut node_start = None;
+    let mut in_comment = false;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if in_comment {
+            in_comment = c != '\n';
+            continue;
+        }
+        match c {
+            '/' if chars.peek().is_some_and(|(_, next)| *next == '/') => in_comment = true,
+            '{' => {
+                node_start.get_or_insert(i);
+                depth += 1;
+            }
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    if let Some(start) = node_start.take() {
+                        spans.push((start, end_with_trailing_comment(text, i + 1)));
+                    }
+                }
+            }
+            c if depth == 0 && !c.is_whitespace() => {
+                node_start.get_or_insert(i);
+            }
+            _ => (),
+        }
+    }
+    // A node that is never closed extends to the end of the text
+    if let Some(start) = node_start {
+        spans.push((start, text.len()));
+    }
+    spans
+}
+
+fn end_with_trailing_comment(text: &str, end: usize) -> usize {
+    let rest_of_line = text[end..].split(['\r', '\n']).next().unwrap_or_default();
+    if rest_of_line.trim_start().starts_with("//") {
+        end + rest_of_line.len()
+    } else {
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{parse, DocItem};
+
+    use super::*;
+
+    #[test]
+    fn test_reparse_edited_node() {
+        let text = "first\n{\n\tkey = val\n}\n// comment\nsecond // id\n{\n\tkey = val\n\tinner { a = b }\n} // trailing\nthird {}\n";
+        let edited = text.replace("inner { a = b }", "inner { a = b }\n\tnew = val");
+        let (node, errors) = reparse_node_at(&edited, Position::new(10, 4)).unwrap();
+        assert!(errors.is_empty());
+
+        let (doc, _errors) = parse(&edited);
+        let DocItem::Node(expected) = &doc.statements[2] else {
+            panic!("expected a node, got {:#?}", doc.statements[2]);
+        };
+        assert_eq!(&node, expected);
+        assert_eq!(node.get_range().start, Position::new(6, 1));
+    }
+
+    #[test]
+    fn test_reparse_outside_node() {
+        let text = "first {}\n\n// comment\n";
+        assert!(reparse_node_at(text, Position::new(2, 1)).is_none());
+        assert!(reparse_node_at(text, Position::new(3, 4)).is_none());
+        assert!(reparse_node_at(text, Position::new(1, 3)).is_some());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::parser::{DocItem, Document, KeyVal, Node, NodeItem, Ranged};
+
+/// Keeps the alignment of assignment operators where the author already aligned them, instead of printing a single space before each operator.
+///
+/// A run of assignments on adjacent lines, with no comments, empty lines or nodes in between, is aligned if the `=` of the operator is the same distance from the start of the key on every line.
+/// Tabs before the operator are measured to the next tab stop, with a tab stop every 4 columns from the start of the key, and are kept as written in aligned runs.
+/// Runs that are not aligned are printed as usual. This is a middle ground between not aligning at all, and always aligning similar keys with [`super::assignment_padding()`]
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{
+///     parser::{parse, ASTPrint, PrintSettings},
+///     transformer::preserve_alignment,
+/// };
+///
+/// let (doc, _errors) = parse("node\n{\n  a     = 1\n  long  = 2\n}\n");
+/// let doc = preserve_alignment(doc);
+/// assert_eq!(
+///     doc.ast_print(0, "\t", "\n", &PrintSettings::new(None)),
+///     "node\n{\n\ta     = 1\n\tlong  = 2\n}\n"
+/// );
+/// ```
+#[must_use]
+pub fn preserve_alignment(mut doc: Document) -> Document {
+    for item in &mut doc.statements {
+        if let DocItem::Node(node) = item {
+            align_node_items(node);
+        }
+    }
+    doc
+}
+
+fn align_node_items(node: &mut Ranged<Node>) {
+    let mut run = vec![];
+    for item in &mut node.block {
+        match item {
+            NodeItem::KeyVal(kv) => run.push(kv),
+            NodeItem::Node(node) => {
+                align_run(&mut run);
+                align_node_items(node);
+            }
+            NodeItem::Comment(_) | NodeItem::EmptyLine(_) | NodeItem::Error(_) => {
+                align_run(&mut run);
+            }
+        }
+    }
+    align_run(&mut run);
+}
+
+/// Columns between tab stops, counted from the start of the key
+const TAB_WIDTH: usize = 4;
+
+/// Width of the text up to and including the assignment operator, with tabs before the operator expanded to the next tab stop
+fn source_width(kv: &KeyVal) -> usize {
+    let key_width = kv.left_side().chars().count();
+    let operator_column = kv
+        .operator_whitespace()
+        .chars()
+        .fold(key_width, |column, c| match c {
+            '\t' => (column / TAB_WIDTH + 1) * TAB_WIDTH,
+            _ => column + 1,
+        });
+    operator_column + kv.assignment_operator.to_string().len()
+}
+
+/// Pads the assignments in the run to their width in the source, if they all had the same width, and empties the run
+fn align_run(run: &mut Vec<&mut Ranged<KeyVal>>) {
+    if run.len() > 1 {
+        let width = source_width(run[0]);
+        if run.iter().all(|kv| source_width(kv) == width) {
+            let has_tabs = run.iter().any(|kv| kv.operator_whitespace().contains('\t'));
+            for kv in run.iter_mut() {
+                if has_tabs {
+                    kv.keep_operator_whitespace();
+                } else {
+                    // One space is always printed before the operator
+                    let operator_len = kv.assignment_operator.to_string().len();
+                    kv.set_key_padding(width.saturating_sub(operator_len + 1));
+                }
+            }
+        }
+    }
+    run.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transformer::transformed;
+
+    fn align(input: &str) -> String {
+        transformed(input, None, preserve_alignment)
+    }
+
+    #[test]
+    fn aligned_stays_aligned() {
+        let input = "node\n{\n    key     = 1\n    longer  = 2\n    @k,0   += 3\n}\n";
+        let expected = "node\n{\n\tkey     = 1\n\tlonger  = 2\n\t@k,0   += 3\n}\n";
+        assert_eq!(align(input), expected);
+    }
+
+    #[test]
+    fn tab_aligned_keeps_tabs() {
+        let input = "node\n{\n\ta\t\t= 1\n\tlong\t= 2\n\tab\t\t= 3\n\n\tab\t= 4\n\tcd\t= 5\n}\n";
+        assert_eq!(align(input), input);
+    }
+
+    #[test]
+    fn unaligned_stays_unaligned() {
+        let input = "node\n{\n\tkey   = 1\n\tlonger    = 2\n}\n";
+        let expected = "node\n{\n\tkey = 1\n\tlonger = 2\n}\n";
+        assert_eq!(align(input), expected);
+    }
+
+    #[test]
+    fn runs_are_separate() {
+        let input =
+            "node\n{\n\ta   = 1\n\tbb  = 2\n\n\tc     = 3\n\tdd = 4\n\tchild\n\t{\n\t\te  = 5\n\t\tf  = 6\n\t}\n}\n";
+        let expected =
+            "node\n{\n\ta   = 1\n\tbb  = 2\n\n\tc = 3\n\tdd = 4\n\tchild\n\t{\n\t\te  = 5\n\t\tf  = 6\n\t}\n}\n";
+        assert_eq!(align(input), expected);
+    }
+}
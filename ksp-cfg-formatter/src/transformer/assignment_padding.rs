@@ -68,10 +68,10 @@ fn handle_node_items(mut node: Ranged<Node>) -> Ranged<Node> {
                 processed.push(NodeItem::Comment(comment));
             }
             NodeItem::KeyVal(kv) => accumulator.push(kv),
-            NodeItem::EmptyLine => {
+            NodeItem::EmptyLine(range) => {
                 processed = fix_kvs(accumulator, processed);
                 accumulator = Vec::new();
-                processed.push(NodeItem::EmptyLine);
+                processed.push(NodeItem::EmptyLine(range));
             }
             NodeItem::Error(_e) => todo!(),
         }
@@ -1,10 +1,27 @@
-use crate::parser::{DocItem, Document, Error, Node, NodeItem, Ranged};
+use crate::parser::{DocItem, Document, Node, NodeItem, Ranged};
+
+use super::TransformError;
 
 /// Moves assignments first in the node, and child nodes last
+///
+/// Comments and empty lines directly above a `KeyVal` or `Node` are kept together with that item when it is moved.
+/// Relative order is preserved within both the assignments and the child nodes.
+/// Comments and empty lines after the last item of a block stay at the end of the block.
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{parser::{parse, ASTPrint, PrintSettings}, transformer::assignments_first};
+///
+/// let (doc, _errors) = parse("node\n{\n\tchild {}\n\tkey = val\n}\n");
+/// let doc = assignments_first(doc).unwrap();
+/// assert_eq!(
+///     doc.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+///     "node\n{\n\tkey = val\n\tchild {}\n}\n"
+/// );
+/// ```
 /// # Errors
-/// Returns an error if an empty line, or a comment is found at the bottom of a node
-// Assume empty line between keys and nodes
-pub fn assignments_first(mut doc: Document) -> Result<Document, Error> {
+/// Returns an error if a node contains a parse error, since it is unknown where the erroneous text belongs
+pub fn assignments_first(mut doc: Document) -> Result<Document, TransformError> {
     let items = doc.statements;
     let mut new_items = vec![];
     for item in items {
@@ -19,57 +36,70 @@ pub fn assignments_first(mut doc: Document) -> Result<Document, Error> {
     Ok(doc)
 }
 
-fn reorder_node_items(mut node: Ranged<Node>) -> Result<Ranged<Node>, Error> {
-    let mut node_items = node.block.clone();
+fn reorder_node_items(mut node: Ranged<Node>) -> Result<Ranged<Node>, TransformError> {
     let mut key_stuff = vec![];
     let mut node_stuff = vec![];
+    // Comments and empty lines waiting for the item they are attached to
+    let mut pending = vec![];
 
-    let mut processing_key: Option<bool> = None;
-    node_items.reverse();
-    for item in node_items {
+    for item in std::mem::take(&mut node.block) {
         match item {
-            NodeItem::Node(node) => {
-                processing_key = Some(false);
-                node_stuff.push(NodeItem::Node(reorder_node_items(node)?));
+            NodeItem::Node(child) => {
+                node_stuff.append(&mut pending);
+                node_stuff.push(NodeItem::Node(reorder_node_items(child)?));
             }
-            NodeItem::Comment(ref c) => match processing_key {
-                Some(true) => key_stuff.push(item),
-                Some(false) => node_stuff.push(item),
-                None => {
-                    return Err(Error {
-                        message: "Found Comment at end of node".to_string(),
-                        range: c.get_range(),
-                        source: String::new(),
-                        severity: crate::parser::Severity::Info,
-                        context: None,
-                    });
-                }
-            },
             NodeItem::KeyVal(_) => {
-                processing_key = Some(true);
+                key_stuff.append(&mut pending);
                 key_stuff.push(item);
             }
-            NodeItem::EmptyLine => match processing_key {
-                Some(true) => key_stuff.push(item),
-                Some(false) => node_stuff.push(item),
-                None => {
-                    return Err(Error {
-                        message: "Found Empty Line at end of node".to_string(),
-                        range: node.get_range().to_end(),
-                        source: String::new(),
-                        severity: crate::parser::Severity::Info,
-                        context: None,
-                    });
-                }
-            },
-            NodeItem::Error(_e) => todo!(),
+            NodeItem::Comment(_) | NodeItem::EmptyLine(_) => pending.push(item),
+            NodeItem::Error(e) => return Err(TransformError::ContainsError(e.get_range())),
         }
     }
-    key_stuff.reverse();
-    node_stuff.reverse();
-    let mut new_node_items = vec![];
-    new_node_items.append(&mut key_stuff);
+    let mut new_node_items = key_stuff;
     new_node_items.append(&mut node_stuff);
+    new_node_items.append(&mut pending);
     node.block = new_node_items;
     Ok(node)
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{parser::parse, transformer::transformed};
+
+    fn reorder(input: &str) -> String {
+        transformed(input, None, |doc| assignments_first(doc).unwrap())
+    }
+
+    #[test]
+    fn keys_move_above_nodes() {
+        let input = "node\n{\n\tchild {}\n\tkey = 1\n\tother\n\t{\n\t\tinner {}\n\t\tkey = 2\n\t}\n\tkey = 3\n}\n";
+        let expected = "node\n{\n\tkey = 1\n\tkey = 3\n\tchild {}\n\tother\n\t{\n\t\tkey = 2\n\t\tinner {}\n\t}\n}\n";
+        assert_eq!(reorder(input), expected);
+    }
+
+    #[test]
+    fn comments_stay_with_items() {
+        let input = "node\n{\n\t// about child\n\tchild {}\n\n\t// about key\n\tkey = 1\n\t// trailing\n}\n";
+        let expected = "node\n{\n\n\t// about key\n\tkey = 1\n\t// about child\n\tchild {}\n\t// trailing\n}\n";
+        assert_eq!(reorder(input), expected);
+    }
+
+    #[test]
+    fn already_ordered_is_unchanged() {
+        let input = "node\n{\n\tkey = 1\n\n\tchild {}\n}\n";
+        assert_eq!(reorder(input), input);
+    }
+
+    #[test]
+    fn error_in_block() {
+        let (doc, _errors) = parse("node\n{\n\tchild {}\n\tgarbage\n}\n");
+        assert!(matches!(
+            assignments_first(doc),
+            Err(TransformError::ContainsError(_))
+        ));
+    }
+}
@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+
+use crate::parser::{DocItem, Document, Node, NodeItem, Ranged};
+
+/// Case to print node identifiers in. See [`normalize_identifier_case()`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// `PART`
+    Upper,
+    /// `part`
+    Lower,
+    /// Identifiers are printed as they are written
+    #[default]
+    Preserve,
+}
+
+/// Changes the case of node identifiers, like `part` to `PART`, including child nodes. Keys, values and names are left unchanged.
+///
+/// KSP and Module Manager compare node identifiers case sensitively in some places, so changing the case can change what a patch does.
+/// Only use this if all the identifiers are known to be case insensitive.
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{
+///     parser::{parse, ASTPrint, PrintSettings},
+///     transformer::{normalize_identifier_case, Case},
+/// };
+///
+/// let (doc, _errors) = parse("@part[foo] { key = val }");
+/// let doc = normalize_identifier_case(doc, Case::Upper);
+/// assert_eq!(
+///     doc.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+///     "@PART[foo] { key = val }\n"
+/// );
+/// ```
+#[must_use]
+pub fn normalize_identifier_case(mut doc: Document, case: Case) -> Document {
+    for item in &mut doc.statements {
+        if let DocItem::Node(node) = item {
+            set_node_case(node, case);
+        }
+    }
+    doc
+}
+
+fn set_node_case(node: &mut Ranged<Node>, case: Case) {
+    let identifier = match case {
+        Case::Upper => node.identifier.to_uppercase(),
+        Case::Lower => node.identifier.to_lowercase(),
+        Case::Preserve => return,
+    };
+    *node.identifier = Cow::Owned(identifier);
+    for item in &mut node.block {
+        if let NodeItem::Node(node) = item {
+            set_node_case(node, case);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transformer::transformed;
+
+    fn normalize(input: &str, case: Case) -> String {
+        transformed(input, None, |doc| normalize_identifier_case(doc, case))
+    }
+
+    #[test]
+    fn upper_case() {
+        let input = "part\n{\n\tname = foo\n\tmodule\n\t{\n\t\tKey = Val\n\t}\n}\n";
+        let expected = "PART\n{\n\tname = foo\n\tMODULE\n\t{\n\t\tKey = Val\n\t}\n}\n";
+        assert_eq!(normalize(input, Case::Upper), expected);
+    }
+
+    #[test]
+    fn lower_case() {
+        let input = "@PART[Foo]:HAS[#Key[Val]]\n{\n\tKey = Val\n}\n";
+        let expected = "@part[Foo]:HAS[#Key[Val]]\n{\n\tKey = Val\n}\n";
+        assert_eq!(normalize(input, Case::Lower), expected);
+    }
+
+    #[test]
+    fn preserve() {
+        let input = "Part\n{\n\tkey = val\n}\n";
+        assert_eq!(normalize(input, Case::Preserve), input);
+    }
+}
@@ -1,5 +1,41 @@
+mod alignment;
 mod assignment_padding;
 mod assignments_first;
+mod identifier_case;
+mod rewrite_values;
+mod sort_mm_clauses;
+mod sort_nodes;
+mod value_whitespace;
 
+pub use alignment::preserve_alignment;
 pub use assignment_padding::assignment_padding;
 pub use assignments_first::assignments_first;
+pub use identifier_case::{normalize_identifier_case, Case};
+pub use rewrite_values::rewrite_values;
+pub use sort_mm_clauses::sort_mm_clauses;
+pub use sort_nodes::sort_nodes;
+pub use value_whitespace::trim_value_whitespace;
+
+use crate::parser::Range;
+
+#[cfg(test)]
+/// Parses `source`, which must have no errors, applies `transform`, and prints the result with tabs and `collapse` like the `inline` setting of [`crate::Formatter::new()`]
+pub(crate) fn transformed(
+    source: &str,
+    collapse: Option<bool>,
+    transform: impl FnOnce(crate::parser::Document) -> crate::parser::Document,
+) -> String {
+    use crate::parser::{parse, ASTPrint, PrintSettings};
+
+    let (doc, errors) = parse(source);
+    assert!(errors.is_empty(), "{errors:#?}");
+    transform(doc).ast_print(0, "\t", "\n", &PrintSettings::new(collapse))
+}
+
+/// Error returned when a transformation can not be applied to the AST
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TransformError {
+    /// The AST contains a parse error, so the transformation is not safe to perform
+    #[error("can not transform a node containing a parse error at {0}")]
+    ContainsError(Range),
+}
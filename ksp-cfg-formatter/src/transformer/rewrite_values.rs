@@ -0,0 +1,54 @@
+use std::borrow::Cow;
+
+use crate::parser::{DocItem, Document, Node, NodeItem, Ranged};
+
+/// Calls `rewriter` with the value of every assignment, and replaces the value with the returned string. Values where `rewriter` returns `None` are left unchanged.
+///
+/// Only the values are passed to `rewriter`; keys and comments are never changed.
+/// Returned strings containing `}`, `//`, `\n` or `\r` are ignored, since the value would end there when parsed again.
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{
+///     parser::{parse, ASTPrint, PrintSettings},
+///     transformer::rewrite_values,
+/// };
+///
+/// let (doc, _errors) = parse("node { key = 1.50 }");
+/// let doc = rewrite_values(doc, &|val| val.parse::<f64>().ok().map(|n| n.to_string()));
+/// assert_eq!(
+///     doc.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+///     "node { key = 1.5 }\n"
+/// );
+/// ```
+#[must_use]
+pub fn rewrite_values<'a>(
+    mut doc: Document<'a>,
+    rewriter: &dyn Fn(&str) -> Option<String>,
+) -> Document<'a> {
+    for item in &mut doc.statements {
+        if let DocItem::Node(node) = item {
+            rewrite_node_values(node, rewriter);
+        }
+    }
+    doc
+}
+
+fn rewrite_node_values(node: &mut Ranged<Node>, rewriter: &dyn Fn(&str) -> Option<String>) {
+    for item in &mut node.block {
+        match item {
+            NodeItem::Node(node) => rewrite_node_values(node, rewriter),
+            NodeItem::KeyVal(kv) => {
+                if let Some(value) = rewriter(&kv.val).filter(|value| is_valid_value(value)) {
+                    *kv.val = Cow::Owned(value);
+                }
+            }
+            NodeItem::Comment(_) | NodeItem::EmptyLine(_) | NodeItem::Error(_) => (),
+        }
+    }
+}
+
+/// Whether the text can be printed as a value without ending it early
+fn is_valid_value(value: &str) -> bool {
+    !value.contains(['}', '\n', '\r']) && !value.contains("//")
+}
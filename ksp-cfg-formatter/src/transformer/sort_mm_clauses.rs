@@ -0,0 +1,108 @@
+use crate::parser::{
+    DocItem, Document, HasBlock, HasPredicate, NeedsBlock, Node, NodeItem, Ranged,
+};
+
+/// Sorts the predicates of `:HAS` blocks and the clauses of `:NEEDS` blocks alphabetically by their printed text, for deterministic diffs.
+/// `:HAS[#b[1],#a[2]]` is sorted to `:HAS[#a[2],#b[1]]`, and `:NEEDS[B,A|C]` to `:NEEDS[A|C,B]`.
+///
+/// All predicates and clauses are combined with AND, so the order does not change which nodes are matched.
+/// It can still matter in rare cases, for example when a mod relies on the order of the checks, so this is opt-in.
+/// Mods combined with `|` inside a clause are left in their order.
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{
+///     parser::{parse, ASTPrint, PrintSettings},
+///     transformer::sort_mm_clauses,
+/// };
+///
+/// let (doc, _errors) = parse("@PART:HAS[#b,#a] {}");
+/// let doc = sort_mm_clauses(doc);
+/// assert_eq!(
+///     doc.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+///     "@PART:HAS[#a,#b] {}\n"
+/// );
+/// ```
+#[must_use]
+pub fn sort_mm_clauses(mut doc: Document) -> Document {
+    for item in &mut doc.statements {
+        if let DocItem::Node(node) = item {
+            sort_node_clauses(node);
+        }
+    }
+    doc
+}
+
+fn sort_node_clauses(node: &mut Ranged<Node>) {
+    if let Some(has) = &mut node.has {
+        sort_has(has);
+    }
+    if let Some(needs) = &mut node.needs {
+        sort_needs(needs);
+    }
+    for item in &mut node.block {
+        match item {
+            NodeItem::Node(child) => sort_node_clauses(child),
+            NodeItem::KeyVal(key_val) => {
+                if let Some(needs) = &mut key_val.needs {
+                    sort_needs(needs);
+                }
+            }
+            NodeItem::Comment(_) | NodeItem::EmptyLine(_) | NodeItem::Error(_) => (),
+        }
+    }
+}
+
+fn sort_has(has: &mut HasBlock) {
+    // Nested blocks are sorted first, so the predicates are compared as they are printed
+    for predicate in &mut has.predicates {
+        if let HasPredicate::NodePredicate {
+            has_block: Some(has_block),
+            ..
+        } = &mut **predicate
+        {
+            sort_has(has_block);
+        }
+    }
+    has.predicates
+        .sort_by_cached_key(std::string::ToString::to_string);
+}
+
+fn sort_needs(needs: &mut NeedsBlock) {
+    needs
+        .or_clauses
+        .sort_by_cached_key(std::string::ToString::to_string);
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transformer::transformed;
+
+    fn sort(input: &str) -> String {
+        transformed(input, Some(true), sort_mm_clauses)
+    }
+
+    #[test]
+    fn sorts_has_predicates() {
+        assert_eq!(
+            sort("@PART:HAS[#b[1],#a[2]] {}\n"),
+            "@PART:HAS[#a[2],#b[1]] {}\n"
+        );
+        // Nested blocks are sorted, and sorted before their parent is
+        assert_eq!(
+            sort("@PART:HAS[@MODULE[x]:HAS[#z,#y],@MODULE[x]:HAS[#x]] {}\n"),
+            "@PART:HAS[@MODULE[x]:HAS[#x],@MODULE[x]:HAS[#y,#z]] {}\n"
+        );
+    }
+
+    #[test]
+    fn sorts_needs_clauses() {
+        assert_eq!(
+            sort("@PART:NEEDS[B,!A,C|A] { @key:NEEDS[y,x] = val }\n"),
+            "@PART:NEEDS[!A,B,C|A] { @key:NEEDS[x,y] = val }\n"
+        );
+    }
+}
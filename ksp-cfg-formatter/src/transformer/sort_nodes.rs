@@ -0,0 +1,116 @@
+use crate::parser::{DocItem, Document, Node, NodeItem, Ranged};
+
+/// Sorts child nodes alphabetically by their [`Node::header()`], leaving assignments in their original order
+///
+/// Only runs of nodes directly following each other are sorted. Comments, empty lines, assignments and errors between nodes keep the nodes on each side apart.
+/// Nodes that are patches, with an operator or a path, or that have a `:HAS`, `:NEEDS` or pass clause, also keep the nodes on each side apart and never move,
+/// since they act on the nodes above them. Top level nodes are never reordered, since the order of patches can matter.
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{parser::{parse, ASTPrint, PrintSettings}, transformer::sort_nodes};
+///
+/// let (doc, _errors) = parse("node\n{\n\tb {}\n\ta {}\n}\n");
+/// let doc = sort_nodes(doc);
+/// assert_eq!(
+///     doc.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+///     "node\n{\n\ta {}\n\tb {}\n}\n"
+/// );
+/// ```
+#[must_use]
+pub fn sort_nodes(mut doc: Document) -> Document {
+    for item in &mut doc.statements {
+        if let DocItem::Node(node) = item {
+            sort_node_items(node);
+        }
+    }
+    doc
+}
+
+fn sort_node_items(node: &mut Ranged<Node>) {
+    let mut new_items = vec![];
+    let mut run = vec![];
+    for item in std::mem::take(&mut node.block) {
+        match item {
+            NodeItem::Node(mut child) => {
+                sort_node_items(&mut child);
+                if is_barrier(&child) {
+                    push_sorted(&mut new_items, &mut run);
+                    new_items.push(NodeItem::Node(child));
+                } else {
+                    run.push(child);
+                }
+            }
+            item => {
+                push_sorted(&mut new_items, &mut run);
+                new_items.push(item);
+            }
+        }
+    }
+    push_sorted(&mut new_items, &mut run);
+    node.block = new_items;
+}
+
+/// Indicates if the node has to stay in place, as moving it could change what Module Manager does
+fn is_barrier(node: &Node) -> bool {
+    node.is_patch() || node.has.is_some() || node.needs.is_some() || node.pass.is_some()
+}
+
+/// Sorts the run of nodes, and moves them to the end of `items`
+fn push_sorted<'a>(items: &mut Vec<NodeItem<'a>>, run: &mut Vec<Ranged<Node<'a>>>) {
+    run.sort_by_cached_key(|node| node.header());
+    items.extend(run.drain(..).map(NodeItem::Node));
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transformer::transformed;
+
+    fn sort(input: &str) -> String {
+        transformed(input, None, sort_nodes)
+    }
+
+    #[test]
+    fn siblings_are_sorted() {
+        let input =
+            "node\n{\n\tkey = 2\n\tc {}\n\tb\n\t{\n\t\tz {}\n\t\ty {}\n\t}\n\ta {}\n\tkey = 1\n}\n";
+        let expected =
+            "node\n{\n\tkey = 2\n\ta {}\n\tb\n\t{\n\t\ty {}\n\t\tz {}\n\t}\n\tc {}\n\tkey = 1\n}\n";
+        assert_eq!(sort(input), expected);
+    }
+
+    #[test]
+    fn comments_are_barriers() {
+        let input = "node\n{\n\tb {}\n\t// about a\n\ta {}\n}\n";
+        assert_eq!(sort(input), input);
+        let input = "node\n{\n\tb {}\n\n\ta {}\n}\n";
+        assert_eq!(sort(input), input);
+    }
+
+    #[test]
+    fn patches_are_barriers() {
+        // `@c` sorts before `c`, but has to stay below the node it edits
+        let input = "@PART\n{\n\tc {}\n\t@c\n\t{\n\t\tkey = val\n\t}\n\tb {}\n\ta {}\n}\n";
+        let expected = "@PART\n{\n\tc {}\n\t@c\n\t{\n\t\tkey = val\n\t}\n\ta {}\n\tb {}\n}\n";
+        assert_eq!(sort(input), expected);
+        for barrier in [
+            "!b {}",
+            "#../b {}",
+            "b:HAS[#x] {}",
+            "b:NEEDS[Mod] {}",
+            "b:FOR[Mod] {}",
+        ] {
+            let input = format!("node\n{{\n\tc {{}}\n\t{barrier}\n\ta {{}}\n}}\n");
+            assert_eq!(sort(&input), input);
+        }
+    }
+
+    #[test]
+    fn top_level_is_kept() {
+        let input = "b {}\na {}\n";
+        assert_eq!(sort(input), input);
+    }
+}
@@ -0,0 +1,84 @@
+use itertools::Itertools;
+use std::borrow::Cow;
+
+use crate::parser::{DocItem, Document, Node, NodeItem, Ranged};
+
+/// Collapses runs of spaces inside values to a single space, turning `key = a    b` into `key = a b`.
+///
+/// Values are freeform text, so this is conservative: only spaces are collapsed, and values containing quotes are left unchanged.
+/// If `skip_paths` is set, values that look like paths, containing `/` or `\`, are also left unchanged.
+///
+/// Example:
+/// ```
+/// use ksp_cfg_formatter::{
+///     parser::{parse, ASTPrint, PrintSettings},
+///     transformer::trim_value_whitespace,
+/// };
+///
+/// let (doc, _errors) = parse("node { key = a    b }");
+/// let doc = trim_value_whitespace(doc, true);
+/// assert_eq!(
+///     doc.ast_print(0, "\t", "\n", &PrintSettings::new(Some(true))),
+///     "node { key = a b }\n"
+/// );
+/// ```
+#[must_use]
+pub fn trim_value_whitespace(mut doc: Document, skip_paths: bool) -> Document {
+    for item in &mut doc.statements {
+        if let DocItem::Node(node) = item {
+            trim_node_values(node, skip_paths);
+        }
+    }
+    doc
+}
+
+fn trim_node_values(node: &mut Ranged<Node>, skip_paths: bool) {
+    for item in &mut node.block {
+        match item {
+            NodeItem::Node(node) => trim_node_values(node, skip_paths),
+            NodeItem::KeyVal(kv) => {
+                let quoted = kv.val.contains(['"', '\'']);
+                let path = skip_paths && kv.val.contains(['/', '\\']);
+                if !quoted && !path && kv.val.contains("  ") {
+                    *kv.val = Cow::Owned(kv.val.split(' ').filter(|s| !s.is_empty()).join(" "));
+                }
+            }
+            NodeItem::Comment(_) | NodeItem::EmptyLine(_) | NodeItem::Error(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transformer::transformed;
+
+    fn trim(input: &str, skip_paths: bool) -> String {
+        transformed(input, None, |doc| trim_value_whitespace(doc, skip_paths))
+    }
+
+    #[test]
+    fn collapses_spaces() {
+        let input =
+            "node\n{\n\tkey = a    b  c // a  comment\n\tchild\n\t{\n\t\tkey = x  y\n\t}\n}\n";
+        let expected =
+            "node\n{\n\tkey = a b c // a  comment\n\tchild\n\t{\n\t\tkey = x y\n\t}\n}\n";
+        assert_eq!(trim(input, false), expected);
+    }
+
+    #[test]
+    fn conservative() {
+        // Tabs and quoted values are left unchanged
+        let input = "node\n{\n\tkey = a\t\tb\n\tkey = \"a  b\"\n}\n";
+        assert_eq!(trim(input, false), input);
+    }
+
+    #[test]
+    fn skip_paths() {
+        let input = "node\n{\n\tkey = a/b  c\n}\n";
+        assert_eq!(trim(input, true), input);
+        assert_eq!(trim(input, false), "node\n{\n\tkey = a/b c\n}\n");
+    }
+}
@@ -28,7 +28,7 @@ gen_test!(simple, "tests/simple.cfg", true);
 
 gen_test!(one_line_nodes, "tests/one_line_nodes.cfg", true);
 
-gen_test!(sock, "tests/sock.cfg", false);
+gen_test!(sock, "tests/SOCK.cfg", false);
 
 gen_test!(rn_cygnus, "tests/RO_RN_Cygnus.cfg", false);
 
@@ -1,4 +1,8 @@
-use ksp_cfg_formatter::parse_to_ast;
+use ksp_cfg_formatter::{
+    parse_partial, parse_to_ast,
+    parser::{codes, DocItem, Position, Range},
+    unbalanced_brackets, validate,
+};
 #[cfg(test)]
 use std::{
     fs,
@@ -22,6 +26,79 @@ fn error_test() {
     }
 }
 
+#[test]
+fn partial_document_is_kept() {
+    let input = "broken\n{\n\tkey = val\n\tgarbage\n}\ngood\n{\n\tkey = val\n}\n";
+    let (doc, errors, _diagnostics) = parse_partial(input);
+    assert!(!errors.is_empty());
+    let good = doc.statements.iter().find_map(|item| match item {
+        DocItem::Node(node) if *node.identifier == "good" => Some(node),
+        _ => None,
+    });
+    assert_eq!(good.expect("the good node should be parsed").block.len(), 1);
+}
+
+#[test]
+fn validate_files() {
+    assert!(validate(&read_local_path("tests/simple.cfg")).is_empty());
+    let errors = validate("node\n{\n\tkey = val\n\tgarbage\n}\n");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].range.start, Position::new(4, 2));
+    assert_eq!(errors[0].range.end, Position::new(4, 9));
+}
+
+/// Parses a document with one bad line, checking that only that line is an error, and the nodes around it are kept
+fn check_pathological_line(input: &str, line: u32, message: &str) {
+    let (doc, errors, _diagnostics) = parse_partial(input);
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(errors[0].message, message);
+    assert_eq!(errors[0].range.start.line, line);
+    assert_eq!(errors[0].range.end.line, line);
+    let nodes: Vec<&str> = doc
+        .statements
+        .iter()
+        .filter_map(|item| match item {
+            DocItem::Node(node) => Some(&**node.identifier),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(nodes, vec!["a", "b"]);
+}
+
+#[test]
+fn lone_operator() {
+    check_pathological_line(
+        "a {}\n%\nb {}\n",
+        2,
+        "unexpected `%`. Expected a node or key after the operator",
+    );
+    check_pathological_line(
+        "a\n{\n\tkey = val\n\t%\n\tkey = val\n}\nb {}\n",
+        4,
+        "unexpected `%`. Expected a node or key after the operator",
+    );
+}
+
+#[test]
+fn stray_brackets() {
+    check_pathological_line(
+        "a {}\n{\nb {}\n",
+        2,
+        "unexpected `{`. Expected a node name before the opening bracket",
+    );
+    check_pathological_line(
+        "a\n{\n\tkey = val\n\t{\n\tkey = val\n}\nb {}\n",
+        4,
+        "unexpected `{`. Expected a node name before the opening bracket",
+    );
+    check_pathological_line(
+        "a {}\n}\nb {}\n",
+        2,
+        "unexpected `}`. There is no node to close",
+    );
+    check_pathological_line("a {}\n]\nb {}\n", 2, "unexpected `]`");
+}
+
 fn files_from_path(path: &String) -> Vec<String> {
     let mut paths = Vec::new();
     for path in WalkDir::new(path).into_iter().filter_map(Result::ok) {
@@ -36,3 +113,70 @@ fn files_from_path(path: &String) -> Vec<String> {
     }
     paths
 }
+
+#[test]
+fn text_after_closing_bracket() {
+    let errors = validate("node { } leftover\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(
+        errors[0].message,
+        "unexpected `leftover` after the closing bracket. Only a comment can follow `}` on the same line"
+    );
+    assert_eq!(errors[0].range.start, Position::new(1, 10));
+    assert_eq!(errors[0].range.end, Position::new(1, 18));
+    // Inside a node
+    let errors = validate("a\n{\n\tnode { } leftover\n}\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(errors[0].range.start, Position::new(3, 11));
+    assert_eq!(errors[0].range.end, Position::new(3, 19));
+    // Comments and other nodes are allowed
+    assert!(validate("node { } // comment\n").is_empty());
+    assert!(validate("a { } b { }\n").is_empty());
+}
+
+#[test]
+fn top_level_assignment() {
+    check_pathological_line(
+        "a {}\nfoo = bar\nb {}\n",
+        2,
+        "assignments must be inside a node",
+    );
+    let errors = validate("foo = bar\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(
+        errors[0].severity,
+        ksp_cfg_formatter::parser::Severity::Error
+    );
+    assert_eq!(errors[0].message, "assignments must be inside a node");
+    assert_eq!(errors[0].range.start, Position::new(1, 1));
+    assert_eq!(errors[0].range.end, Position::new(1, 10));
+}
+
+#[test]
+fn extra_closing_bracket() {
+    let errors = unbalanced_brackets("node\n{\n\tkey = val\n}\n}\n// }\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(errors[0].code, codes::UNEXPECTED_TEXT);
+    assert_eq!(errors[0].range, Range::new(5, 1, 5, 2));
+}
+
+#[test]
+fn missing_closing_bracket() {
+    let errors = unbalanced_brackets("a\n{\n\tb\n\t{ // {\n\t\tkey = val\n}\nc {}\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(errors[0].code, codes::UNCLOSED_BRACKET);
+    // The outer node is the one left open, as the inner node is closed by its `}`
+    assert_eq!(errors[0].range, Range::new(2, 1, 2, 2));
+    assert!(unbalanced_brackets("a { b { } }").is_empty());
+}
+
+#[test]
+fn bracket_in_value() {
+    assert!(unbalanced_brackets("node\n{\n\tkey = a{b\n}\n").is_empty());
+    assert!(unbalanced_brackets("node { key = a{b }\n").is_empty());
+    // A `=` in a `:HAS` block does not start a value
+    assert!(unbalanced_brackets("@PART:HAS[#key[a=b]] {\n}\n").is_empty());
+    let errors = unbalanced_brackets("node\n{\n\tkey = a{b\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(errors[0].range, Range::new(2, 1, 2, 2));
+}
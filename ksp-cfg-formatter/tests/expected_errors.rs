@@ -17,6 +17,7 @@ fn missing_closing_bracket() {
     let res = ksp_cfg_formatter::parser::parse(&text).1;
     let exp_err = vec![ksp_cfg_formatter::parser::Error {
         severity: ksp_cfg_formatter::parser::Severity::Error,
+        code: "unclosed_bracket",
         message: "Expected closing `]`".to_string(),
         range: ksp_cfg_formatter::parser::Range {
             start: ksp_cfg_formatter::parser::Position { line: 1, col: 11 },
@@ -32,4 +33,18 @@ fn missing_closing_bracket() {
         )),
     }];
     assert_eq!(res, exp_err);
+    assert_eq!(
+        res[0].code,
+        ksp_cfg_formatter::parser::codes::UNCLOSED_BRACKET
+    );
+}
+
+#[test]
+fn missing_closing_brace() {
+    let errors = ksp_cfg_formatter::validate("node\n{\n\tkey = val\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+    assert_eq!(
+        errors[0].code,
+        ksp_cfg_formatter::parser::codes::UNCLOSED_BRACKET
+    );
 }
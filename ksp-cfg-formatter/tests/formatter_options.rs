@@ -0,0 +1,399 @@
+use ksp_cfg_formatter::{parser::Range, BraceStyle, Formatter, Indentation, LineReturn};
+#[cfg(test)]
+use pretty_assertions::assert_eq;
+
+fn format(formatter: Formatter, text: &str) -> String {
+    formatter.format_text(text).unwrap()
+}
+
+#[test]
+fn final_newline_keep() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    assert_eq!(format(formatter, "node {}\n\n"), "node {}\n\n");
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    assert_eq!(format(formatter, "node {}"), "node {}\n");
+}
+
+#[test]
+fn final_newline_ensure() {
+    for input in ["node {}", "node {}\n", "node {}\n\n\n"] {
+        let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+            .ensure_final_newline(Some(true));
+        assert_eq!(format(formatter, input), "node {}\n");
+    }
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::CRLF)
+        .ensure_final_newline(Some(true));
+    assert_eq!(format(formatter, "node {}\n\n"), "node {}\r\n");
+}
+
+#[test]
+fn final_newline_strip() {
+    for input in ["node {}", "node {}\n", "node {}\n\n\n"] {
+        let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+            .ensure_final_newline(Some(false));
+        assert_eq!(format(formatter, input), "node {}");
+    }
+}
+
+#[test]
+fn format_many() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    let results = formatter.format_many(["a { key = val }\n", "b\n{\n}\n", "c {"]);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0], Ok("a\n{\n\tkey = val\n}\n".to_owned()));
+    assert_eq!(results[1], Ok("b {}\n".to_owned()));
+    assert!(results[2].is_err());
+}
+
+#[test]
+fn never_inline_expands_single_item_nodes() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(
+        format(formatter, "node { key = val }\n"),
+        "node\n{\n\tkey = val\n}\n"
+    );
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(
+        format(formatter, "node { child { key = val } }\n"),
+        "node\n{\n\tchild\n\t{\n\t\tkey = val\n\t}\n}\n"
+    );
+}
+
+#[test]
+fn never_inline_keeps_empty_nodes() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(format(formatter, "node\n{\n}\n"), "node {}\n");
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(format(formatter, "node { }\n"), "node {}\n");
+}
+
+#[test]
+fn brace_style_own_line() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+        .brace_style(BraceStyle::OwnLine);
+    assert_eq!(
+        format(formatter, "node {\n\tkey = val\n\tchild { key = val }\n}\n"),
+        "node\n{\n\tkey = val\n\tchild\n\t{\n\t\tkey = val\n\t}\n}\n"
+    );
+}
+
+#[test]
+fn brace_style_same_line() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+        .brace_style(BraceStyle::SameLine);
+    assert_eq!(
+        format(
+            formatter,
+            "node // comment\n{\n\tkey = val\n\tchild { key = val }\n} // end\n"
+        ),
+        "node { // comment\n\tkey = val\n\tchild {\n\t\tkey = val\n\t}\n} // end\n"
+    );
+    // Short and empty nodes are not affected
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+        .brace_style(BraceStyle::SameLine);
+    assert_eq!(
+        format(formatter, "node\n{\n\tkey = val\n}\nempty\n{\n}\n"),
+        "node { key = val }\nempty {}\n"
+    );
+}
+
+#[test]
+fn normalize_comments() {
+    let input = "//x\n// x\n//// divider\nnode //id\n{\n\tkey = val //kv\n\t//inner\n} //end\n";
+    let formatter =
+        Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF).normalize_comments(true);
+    assert_eq!(
+        format(formatter, input),
+        "// x\n// x\n//// divider\nnode // id\n{\n\tkey = val // kv\n\t// inner\n} // end\n"
+    );
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(format(formatter, input), input);
+    // Trailing whitespace is removed
+    let formatter =
+        Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF).normalize_comments(true);
+    assert_eq!(
+        format(formatter, "// foo   \nnode {} // bar \t\n"),
+        "// foo\nnode {} // bar\n"
+    );
+}
+
+#[test]
+fn format_best_effort() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    let input = "= broken\ngood {   key   =   val }\n";
+    assert!(formatter.format_text(input).is_err());
+    let (output, errors) = formatter.format_best_effort(input);
+    assert_eq!(output, "= broken\ngood\n{\n\tkey = val\n}\n");
+    assert_eq!(errors.len(), 1, "{errors:#?}");
+}
+
+#[test]
+fn trailing_comment_after_closing_bracket() {
+    for inline in [Some(true), Some(false), None] {
+        let formatter = Formatter::new(Indentation::Tabs, inline, LineReturn::LF);
+        assert_eq!(format(formatter, "node { }//c\n"), "node {} //c\n");
+    }
+    for inline in [Some(false), None] {
+        let formatter = Formatter::new(Indentation::Tabs, inline, LineReturn::LF);
+        assert_eq!(
+            format(formatter, "node\n{\n\tk = v\n}\t  //c\n"),
+            "node\n{\n\tk = v\n} //c\n"
+        );
+    }
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    assert_eq!(
+        format(formatter, "node { k = v }    //c\n"),
+        "node { k = v } //c\n"
+    );
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(
+        format(formatter, "node { k = v }//c\n"),
+        "node\n{\n\tk = v\n} //c\n"
+    );
+}
+
+#[test]
+fn check() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(formatter.check("node\n{\n\tkey = val\n}\n"), Ok(()));
+    let errors = formatter.check("node\n{\n\tkey = val\n").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    // Failing silently only affects the formatted output
+    let formatter = formatter.fail_silent();
+    assert!(formatter.check("node\n{\n\tkey = val\n").is_err());
+}
+
+#[test]
+fn sort_nodes() {
+    let input = "node\n{\n\tkey = b\n\tb {}\n\ta {}\n\tkey = a\n\t// comment\n\td {}\n\tc {}\n}\n";
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    assert_eq!(format(formatter, input), input);
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF).sort_nodes(true);
+    assert_eq!(
+        format(formatter, input),
+        "node\n{\n\tkey = b\n\ta {}\n\tb {}\n\tkey = a\n\t// comment\n\tc {}\n\td {}\n}\n"
+    );
+}
+
+#[test]
+fn format_node_at_path() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    let text = "PART\n{\n\tname = foo\n\tMODULE\n\t{\n\t\tname = bar\n\t\tkey   =   val\n\t}\n}\n";
+    assert_eq!(
+        formatter.format_node_at_path(text, "PART[foo]/MODULE[bar]"),
+        Some("MODULE\n{\n\tname = bar\n\tkey = val\n}\n".to_owned())
+    );
+    assert_eq!(
+        formatter.format_node_at_path(text, "PART[foo]/MODULE[baz]"),
+        None
+    );
+}
+
+#[test]
+fn indentation_new() {
+    let text = "node { key = val }";
+    let formatter = Formatter::new(Indentation::new(true, 2), Some(false), LineReturn::LF);
+    assert_eq!(format(formatter, text), "node\n{\n  key = val\n}\n");
+    let formatter = Formatter::new(Indentation::new(false, 2), Some(false), LineReturn::LF);
+    assert_eq!(format(formatter, text), "node\n{\n\tkey = val\n}\n");
+}
+
+#[test]
+fn blank_line_between_top_level() {
+    let formatter = || {
+        Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF)
+            .blank_line_between_top_level(true)
+    };
+    // Adjacent nodes get an empty line between them
+    assert_eq!(
+        format(formatter(), "a {}\nb {}\nc {}\n"),
+        "a {}\n\nb {}\n\nc {}\n"
+    );
+    // Existing empty lines are kept as is
+    assert_eq!(format(formatter(), "a {}\n\nb {}\n"), "a {}\n\nb {}\n");
+    assert_eq!(format(formatter(), "a {}\n\n\nb {}\n"), "a {}\n\n\nb {}\n");
+    // Comments above a node stay with the node
+    assert_eq!(
+        format(formatter(), "// first\na {}\n// second\nb {}\n"),
+        "// first\na {}\n\n// second\nb {}\n"
+    );
+    // Child nodes are not affected
+    assert_eq!(
+        format(formatter(), "a\n{\n\tb {}\n\tc {}\n}\n"),
+        "a\n{\n\tb {}\n\tc {}\n}\n"
+    );
+    // Disabled by default
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    assert_eq!(format(formatter, "a {}\nb {}\n"), "a {}\nb {}\n");
+}
+
+#[test]
+fn format_invalid_utf8() {
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    let bytes = b"node\n{\n\tkey   =   a\xff\xfeb\n}\n";
+    assert_eq!(
+        formatter.format_bytes(bytes).unwrap(),
+        "node\n{\n\tkey = a\u{FFFD}\u{FFFD}b\n}\n"
+    );
+    // Errors point into the replaced text
+    let errors = formatter.format_bytes(b"\xff\xff {").unwrap_err();
+    assert!(!errors.is_empty());
+}
+
+#[test]
+fn empty_document() {
+    for input in ["", "\n", "\r\n", "  \n\t\n", "   "] {
+        for setting in [None, Some(true), Some(false)] {
+            let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::Identify)
+                .ensure_final_newline(setting);
+            assert_eq!(format(formatter, input), "", "{input:?} {setting:?}");
+        }
+    }
+}
+
+#[test]
+fn keep_comment_indentation() {
+    let text = "node\n{\n// Section\n\t\t\t// indented\n  key = val\n\tchild\n\t{\n// Nested section\n\t}\n}\n";
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(
+        format(formatter, text),
+        "node\n{\n\t// Section\n\t// indented\n\tkey = val\n\tchild\n\t{\n\t\t// Nested section\n\t}\n}\n"
+    );
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+        .keep_comment_indentation(true);
+    assert_eq!(
+        format(formatter, text),
+        "node\n{\n// Section\n\t\t\t// indented\n\tkey = val\n\tchild\n\t{\n// Nested section\n\t}\n}\n"
+    );
+    // The whitespace is kept as written, regardless of the indentation used
+    let text =
+        "node\n{\n        // spaces\n\t\t// tabs\n \t // mixed\n\tkey = val // trailing\n}\n";
+    for (indentation, key) in [(Indentation::Tabs, "\t"), (Indentation::Spaces(4), "    ")] {
+        let formatter =
+            Formatter::new(indentation, Some(false), LineReturn::LF).keep_comment_indentation(true);
+        assert_eq!(
+            format(formatter, text),
+            format!("node\n{{\n        // spaces\n\t\t// tabs\n \t // mixed\n{key}key = val // trailing\n}}\n")
+        );
+    }
+}
+
+#[test]
+fn empty_blocks_stay_collapsed() {
+    // Empty blocks are always collapsed, so expanding with `inline` set to `Some(false)` only keeps the empty blocks on one line
+    let text = "empty\n{\n}\none { key = val }\nparent\n{\n\tchild {}\n}\n";
+    let formatter = || Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF);
+    assert_eq!(
+        format(formatter(), text),
+        "empty {}\none\n{\n\tkey = val\n}\nparent\n{\n\tchild {}\n}\n"
+    );
+    assert_eq!(
+        format(formatter().space_in_empty_braces(true), text),
+        "empty { }\none\n{\n\tkey = val\n}\nparent\n{\n\tchild { }\n}\n"
+    );
+    let formatter = Formatter::new(Indentation::Tabs, Some(true), LineReturn::LF);
+    assert_eq!(
+        format(formatter, text),
+        "empty {}\none { key = val }\nparent\n{\n\tchild {}\n}\n"
+    );
+}
+
+#[test]
+fn space_in_empty_braces() {
+    let text = "a {}\nb\n{\n}\nc { child {   } }\n";
+    let formatter = || Formatter::new(Indentation::Tabs, None, LineReturn::LF);
+    assert_eq!(
+        format(formatter(), text),
+        "a {}\nb {}\nc\n{\n\tchild {}\n}\n"
+    );
+    assert_eq!(
+        format(formatter().space_in_empty_braces(true), text),
+        "a { }\nb { }\nc\n{\n\tchild { }\n}\n"
+    );
+}
+
+#[test]
+fn value_rewriter() {
+    let text = "node\n{\n\tkey = val // comment\n\tchild { other = 1.5 }\n}\n";
+    let formatter = Formatter::new(Indentation::Tabs, None, LineReturn::LF)
+        .with_value_rewriter(|val| Some(val.to_uppercase()));
+    assert_eq!(
+        format(formatter, text),
+        "node\n{\n\tkey = VAL // comment\n\tchild { other = 1.5 }\n}\n"
+    );
+    // Values where the rewriter returns `None` are unchanged
+    let formatter = Formatter::new(Indentation::Tabs, None, LineReturn::LF)
+        .with_value_rewriter(|val| (val == "val").then(|| "new".to_owned()));
+    assert_eq!(
+        format(formatter, text),
+        "node\n{\n\tkey = new // comment\n\tchild { other = 1.5 }\n}\n"
+    );
+    // Values that would end early when parsed again are ignored
+    for rewritten in ["a } b", "a // b", "a\nb", "a\rb"] {
+        let formatter = Formatter::new(Indentation::Tabs, None, LineReturn::LF)
+            .with_value_rewriter(move |_| Some(rewritten.to_owned()));
+        assert_eq!(format(formatter, text), text);
+    }
+}
+
+#[test]
+fn normalize_indentation_only() {
+    let text = "node\n{\n      key   =  val   // comment {\n\n  child { a = b }\n  other {\n        x = y\n      }\n}\n";
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+        .normalize_indentation_only(true);
+    assert_eq!(
+        format(formatter, text),
+        "node\n{\n\tkey   =  val   // comment {\n\n\tchild { a = b }\n\tother {\n\t\tx = y\n\t}\n}\n"
+    );
+    // Text that is already indented is unchanged, including CRLF line endings
+    let text = "a\r\n{\r\n  b { c = d }\r\n}";
+    let formatter = Formatter::new(Indentation::Spaces(2), Some(false), LineReturn::LF)
+        .normalize_indentation_only(true);
+    assert_eq!(format(formatter, text), text);
+    // Brackets in values don't change the depth
+    let text = "node\n{\nkey = a{b\nx = y\n}\nnext\n{\nz = 1\n}\n";
+    let formatter = Formatter::new(Indentation::Tabs, Some(false), LineReturn::LF)
+        .normalize_indentation_only(true);
+    assert_eq!(
+        format(formatter, text),
+        "node\n{\n\tkey = a{b\n\tx = y\n}\nnext\n{\n\tz = 1\n}\n"
+    );
+}
+
+#[test]
+fn format_range() {
+    let text = "a {   x = 1 }\n\n// b\nb\n{\n    y   = 2\n}\nc {   }\n";
+    let formatter = Formatter::new(Indentation::Tabs, None, LineReturn::LF);
+    let start = text.find('y').unwrap();
+    let (range, output) = formatter.format_range(text, start, start + 1).unwrap();
+    assert_eq!(range, Range::new(4, 1, 7, 2));
+    assert_eq!(output, "b\n{\n\ty = 2\n}");
+
+    // A span over several items replaces all of them, including the empty lines between them
+    let (range, output) = formatter
+        .format_range(text, 0, text.find("// b").unwrap())
+        .unwrap();
+    assert_eq!(range, Range::new(1, 1, 3, 5));
+    assert_eq!(output, "a { x = 1 }\n\n// b");
+
+    // Nothing to format between the items
+    let empty_line = text.find("\n\n").unwrap() + 1;
+    let (range, output) = formatter
+        .format_range(text, empty_line, empty_line)
+        .unwrap();
+    assert_eq!(range, Range::new(2, 1, 2, 1));
+    assert_eq!(output, "");
+
+    // Only the indentation of the lines is changed
+    let (range, output) = Formatter::new(Indentation::Spaces(2), None, LineReturn::LF)
+        .normalize_indentation_only(true)
+        .format_range(text, start, start + 1)
+        .unwrap();
+    assert_eq!(range, Range::new(4, 1, 7, 2));
+    assert_eq!(output, "b\n{\n  y   = 2\n}");
+
+    // Offsets inside a multi-byte char don't panic
+    let (range, output) = formatter.format_range("é {  x = 1 }\n", 1, 2).unwrap();
+    assert_eq!(range, Range::new(1, 1, 1, 13));
+    assert_eq!(output, "é { x = 1 }");
+}
@@ -44,6 +44,11 @@ fn main() -> anyhow::Result<()> {
     let server_capabilities = serde_json::to_value(ServerCapabilities {
         // List of server capabilities
         document_formatting_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(lsp_types::CompletionOptions {
+            trigger_characters: Some(vec![":".to_owned()]),
+            ..Default::default()
+        }),
         text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
         diagnostic_provider: Some(lsp_types::DiagnosticServerCapabilities::Options(
             lsp_types::DiagnosticOptions {
@@ -125,6 +130,8 @@ struct State {
     sender: Sender<lsp_server::Message>,
     outgoing: Outgoing,
     pending_requests: HashMap<lsp_server::RequestId, HandlerFn>,
+    /// The diagnostics last sent for each document, with the version of the document they were computed for
+    diagnostics_cache: HashMap<PathBuf, (i32, Vec<lsp_types::Diagnostic>)>,
 }
 
 impl State {
@@ -135,6 +142,7 @@ impl State {
             sender,
             outgoing: Outgoing::new(),
             pending_requests: HashMap::default(),
+            diagnostics_cache: HashMap::default(),
         }
     }
 
@@ -154,6 +162,8 @@ impl State {
 #[derive(Default)]
 struct DocumentDataBase {
     data_base: HashMap<PathBuf, String>,
+    /// The version of each document, as sent by the client. Increases with every change
+    versions: HashMap<PathBuf, i32>,
 }
 
 impl DocumentDataBase {
@@ -168,6 +178,8 @@ impl DocumentDataBase {
             .map_err(|()| anyhow::format_err!("url is not a file"))?;
         self.data_base
             .insert(key.clone(), params.text_document.text.clone());
+        self.versions
+            .insert(key.clone(), params.text_document.version);
         debug!("inserted file {key:?}");
         Ok(())
     }
@@ -188,6 +200,8 @@ impl DocumentDataBase {
                 .map(|a| a.text.as_str())
                 .collect(),
         );
+        self.versions
+            .insert(key.clone(), params.text_document.version);
         debug!("{:?}", self.data_base.get(&key));
         Ok(())
     }
@@ -201,6 +215,7 @@ impl DocumentDataBase {
             .to_file_path()
             .map_err(|()| anyhow::format_err!("url is not a file"))?;
         self.data_base.remove(&key);
+        self.versions.remove(&key);
         Ok(())
     }
 }
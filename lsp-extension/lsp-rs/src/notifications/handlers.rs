@@ -20,6 +20,9 @@ pub(crate) fn remove_document_from_db(
     state: &mut State,
     not: &lsp_types::DidCloseTextDocumentParams,
 ) -> anyhow::Result<()> {
+    if let Ok(key) = not.text_document.uri.to_file_path() {
+        state.diagnostics_cache.remove(&key);
+    }
     state.data_base.remove_document_from_db(not)
 }
 
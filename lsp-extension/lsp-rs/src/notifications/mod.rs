@@ -40,7 +40,7 @@ impl<'a> NotificationDispatch<'a> {
         N::Params: serde::de::DeserializeOwned,
     {
         let Some(not) = self.notification.take() else {
-            return Ok(self)
+            return Ok(self);
         };
         let params = match not.extract::<N::Params>(N::METHOD) {
             Ok(it) => it,
@@ -1,6 +1,8 @@
 use log::debug;
 use lsp_types::{DiagnosticRelatedInformation, Location, MessageType, ShowMessageRequestParams};
 
+use ksp_cfg_formatter::parser::{DocItem, Node, NodeItem, Path, PathSegment, Range, Ranged};
+
 use super::State;
 
 pub(crate) fn handle_formatting_request(
@@ -37,23 +39,12 @@ pub(crate) fn handle_formatting_request(
 
     // This is where the formatting should be done, by passing in settings and the ´text´
     debug!("formatting text:\n{text}\nwith settings tabs: `{tabs}`, tab size: `{tab_size}`\nother settings: {:?}\n", params.options.properties);
-    let indentation = if tabs {
-        ksp_cfg_formatter::Indentation::Tabs
-    } else {
-        ksp_cfg_formatter::Indentation::Spaces(tab_size as usize)
-    };
-    let new_text_res = ksp_cfg_formatter::Formatter::new(
-        indentation,
-        state.settings.should_collapse,
-        ksp_cfg_formatter::LineReturn::Identify,
-    )
-    .format_text(text);
+    let new_text_res = formatter(&params.options, state.settings.should_collapse).format_text(text);
 
     match new_text_res {
         Ok(new_text) => {
-            let text_edit = text_edit_entire_document(text, new_text)?;
-            let edit = vec![text_edit];
-            Ok(Some(edit))
+            let edits = text_edits_from_diff(text, &new_text)?;
+            Ok(Some(edits))
         }
         Err(_errs) => {
             // TODO: Improve message about formatting failing due to errors
@@ -70,27 +61,58 @@ pub(crate) fn handle_formatting_request(
     }
 }
 
-/// Takes the orignal text and the new text and creates a single edit replacing the entire document
-fn text_edit_entire_document(original: &str, new: String) -> anyhow::Result<lsp_types::TextEdit> {
-    Ok(lsp_types::TextEdit {
-        range: lsp_types::Range {
-            start: lsp_types::Position {
-                line: 0,
-                character: 0,
-            },
-            end: lsp_types::Position {
-                line: original.lines().count().try_into()?,
-                character: original
-                    .lines()
-                    .last()
-                    .unwrap_or_default()
-                    .chars()
-                    .count()
-                    .try_into()?,
+/// Creates the formatter to use, from the editor options and the `shouldCollapse` setting of the extension
+fn formatter(
+    options: &lsp_types::FormattingOptions,
+    should_collapse: Option<bool>,
+) -> ksp_cfg_formatter::Formatter {
+    let indentation =
+        ksp_cfg_formatter::Indentation::new(options.insert_spaces, options.tab_size as usize);
+    ksp_cfg_formatter::Formatter::new(
+        indentation,
+        should_collapse,
+        ksp_cfg_formatter::LineReturn::Identify,
+    )
+}
+
+/// Takes the original text and the new text and creates the edits needed to turn one into the other.
+///
+/// The texts are compared line by line, and an edit is created for each changed group of lines
+fn text_edits_from_diff(original: &str, new: &str) -> anyhow::Result<Vec<lsp_types::TextEdit>> {
+    let diff = similar::TextDiff::from_lines(original, new);
+    let original_lines = diff.old_slices();
+    let new_lines = diff.new_slices();
+    let mut edits = vec![];
+    for op in diff.ops() {
+        if matches!(op, similar::DiffOp::Equal { .. }) {
+            continue;
+        }
+        let old_range = op.old_range();
+        edits.push(lsp_types::TextEdit {
+            range: lsp_types::Range {
+                start: line_start_position(original_lines, old_range.start)?,
+                end: line_start_position(original_lines, old_range.end)?,
             },
-        },
-        new_text: new,
-    })
+            new_text: new_lines[op.new_range()].concat(),
+        });
+    }
+    Ok(edits)
+}
+
+/// Position of the start of the line with index `line`.
+///
+/// If the line is past the last line, the end of the document is returned instead, with the column in UTF-16 code units
+fn line_start_position(lines: &[&str], line: usize) -> anyhow::Result<lsp_types::Position> {
+    match lines.last() {
+        Some(last) if line >= lines.len() && !last.ends_with('\n') => Ok(lsp_types::Position {
+            line: (lines.len() - 1).try_into()?,
+            character: last.encode_utf16().count().try_into()?,
+        }),
+        _ => Ok(lsp_types::Position {
+            line: line.try_into()?,
+            character: 0,
+        }),
+    }
 }
 
 pub(crate) fn handle_diagnostics_request(
@@ -101,23 +123,62 @@ pub(crate) fn handle_diagnostics_request(
     let key = uri
         .to_file_path()
         .map_err(|()| anyhow::format_err!("url is not a file"))?;
+    let items = cached_diagnostics(state, key, |text| document_diagnostics(text, &uri))?;
+    Ok(lsp_types::DocumentDiagnosticReportResult::Report(
+        lsp_types::DocumentDiagnosticReport::Full(lsp_types::RelatedFullDocumentDiagnosticReport {
+            related_documents: None,
+            full_document_diagnostic_report: lsp_types::FullDocumentDiagnosticReport {
+                result_id: None,
+                items,
+            },
+        }),
+    ))
+}
+
+/// Returns the diagnostics for the document at `key`, only calling `compute` if the document changed since the last call
+fn cached_diagnostics(
+    state: &mut State,
+    key: std::path::PathBuf,
+    compute: impl FnOnce(&str) -> Vec<lsp_types::Diagnostic>,
+) -> anyhow::Result<Vec<lsp_types::Diagnostic>> {
     let text = state
         .data_base
         .data_base
         .get(&key)
         .ok_or_else(|| anyhow::format_err!("no text provided"))?;
+    let version = state.data_base.versions.get(&key).copied();
+    if let (Some(version), Some((cached_version, diagnostics))) =
+        (version, state.diagnostics_cache.get(&key))
+    {
+        if version == *cached_version {
+            debug!("using cached diagnostics for version {version} of {key:?}");
+            return Ok(diagnostics.clone());
+        }
+    }
+    let diagnostics = compute(text);
+    if let Some(version) = version {
+        state
+            .diagnostics_cache
+            .insert(key, (version, diagnostics.clone()));
+    }
+    Ok(diagnostics)
+}
+
+/// Parses and lints the text, converting the errors and diagnostics to LSP diagnostics
+fn document_diagnostics(text: &str, uri: &lsp_types::Url) -> Vec<lsp_types::Diagnostic> {
     let (doc, errors) = ksp_cfg_formatter::parser::parse(text);
     let mut disp_errors = vec![];
     for error in errors {
         use lsp_types::DiagnosticSeverity as lsp_sev;
         disp_errors.push(lsp_types::Diagnostic {
-            range: crate::utils::range_to_range(error.range),
+            range: error.range.to_lsp(text),
             severity: Some(crate::utils::sev_to_sev(&error.severity)),
+            code: Some(lsp_types::NumberOrString::String(error.code.to_owned())),
             message: error.message,
             related_information: error.context.clone().map(|context| {
                 vec![DiagnosticRelatedInformation {
                     location: Location {
-                        range: crate::utils::range_to_range(context.get_range()),
+                        range: context.get_range().to_lsp(text),
                         uri: uri.clone(),
                     },
                     message: context.to_string(),
@@ -127,12 +188,12 @@ pub(crate) fn handle_diagnostics_request(
         });
         if let Some(context) = error.context {
             disp_errors.push(lsp_types::Diagnostic {
-                range: crate::utils::range_to_range(context.get_range()),
+                range: context.get_range().to_lsp(text),
                 severity: Some(lsp_sev::HINT),
                 message: context.to_string(),
                 related_information: Some(vec![DiagnosticRelatedInformation {
                     location: Location {
-                        range: crate::utils::range_to_range(error.range),
+                        range: error.range.to_lsp(text),
                         uri: uri.clone(),
                     },
                     message: "original diagnostic".to_string(),
@@ -141,18 +202,400 @@ pub(crate) fn handle_diagnostics_request(
             });
         }
     }
-    let mut items = ksp_cfg_formatter::linter::lint_ast(&doc, Some(uri))
+    let mut items = ksp_cfg_formatter::linter::lint_ast(&doc, text, Some(uri.clone()))
         .iter()
-        .map(crate::utils::diag_to_diag)
+        .map(|diagnostic| crate::utils::diag_to_diag(diagnostic, text))
         .collect();
     disp_errors.append(&mut items);
-    Ok(lsp_types::DocumentDiagnosticReportResult::Report(
-        lsp_types::DocumentDiagnosticReport::Full(lsp_types::RelatedFullDocumentDiagnosticReport {
-            related_documents: None,
-            full_document_diagnostic_report: lsp_types::FullDocumentDiagnosticReport {
-                result_id: None,
-                items: disp_errors,
-            },
-        }),
-    ))
+    disp_errors
+}
+
+pub(crate) fn handle_rename_request(
+    state: &mut State,
+    params: lsp_types::RenameParams,
+) -> anyhow::Result<Option<lsp_types::WorkspaceEdit>> {
+    let uri = params.text_document_position.text_document.uri;
+    let key = uri
+        .to_file_path()
+        .map_err(|()| anyhow::format_err!("url is not a file"))?;
+    let text = state
+        .data_base
+        .data_base
+        .get(&key)
+        .ok_or_else(|| anyhow::format_err!("no text provided"))?;
+    let Some(edits) = rename_edits(
+        text,
+        params.text_document_position.position,
+        &params.new_name,
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(lsp_types::WorkspaceEdit {
+        changes: Some(std::collections::HashMap::from([(uri, edits)])),
+        ..Default::default()
+    }))
+}
+
+/// Creates the edits renaming the node identifier at `position`, and all other uses of it in the text.
+///
+/// Returns `None` if there is no node identifier at `position`
+fn rename_edits(
+    text: &str,
+    position: lsp_types::Position,
+    new_name: &str,
+) -> Option<Vec<lsp_types::TextEdit>> {
+    let (doc, _errors) = ksp_cfg_formatter::parser::parse(text);
+    let mut identifiers = vec![];
+    for item in &doc.statements {
+        if let DocItem::Node(node) = item {
+            node_identifiers(node, &mut identifiers);
+        }
+    }
+    let position = ksp_cfg_formatter::parser::Position::from_lsp(position, text);
+    let (name, _range) = identifiers
+        .iter()
+        .find(|(_name, range)| range.start <= position && position <= range.end)?;
+    Some(
+        identifiers
+            .iter()
+            .filter(|(other, _range)| other == name)
+            .map(|(_name, range)| lsp_types::TextEdit {
+                range: range.to_lsp(text),
+                new_text: new_name.to_owned(),
+            })
+            .collect(),
+    )
+}
+
+/// Collects the identifiers of `node`, its child nodes, and of all path segments in them, together with their ranges
+fn node_identifiers<'a>(node: &'a Ranged<Node>, identifiers: &mut Vec<(&'a str, Range)>) {
+    identifiers.push((&**node.identifier, node.identifier.get_range()));
+    path_identifiers(node.path.as_ref(), identifiers);
+    for item in &node.block {
+        match item {
+            NodeItem::Node(child) => node_identifiers(child, identifiers),
+            NodeItem::KeyVal(key_val) => path_identifiers(key_val.path.as_ref(), identifiers),
+            _ => (),
+        }
+    }
+}
+
+fn path_identifiers<'a>(path: Option<&Ranged<Path<'a>>>, identifiers: &mut Vec<(&'a str, Range)>) {
+    for segment in path.iter().flat_map(|path| &path.segments) {
+        if let PathSegment::NodeName { node, .. } = segment.as_ref() {
+            // The segment starts with the node type
+            let start = segment.get_range().start;
+            let len: u32 = node.chars().count().try_into().unwrap_or(u32::MAX);
+            identifiers.push((
+                node,
+                Range::new(start.line, start.col, start.line, start.col + len),
+            ));
+        }
+    }
+}
+
+pub(crate) fn handle_completion_request(
+    state: &mut State,
+    params: lsp_types::CompletionParams,
+) -> anyhow::Result<Option<lsp_types::CompletionResponse>> {
+    let uri = params.text_document_position.text_document.uri;
+    let key = uri
+        .to_file_path()
+        .map_err(|()| anyhow::format_err!("url is not a file"))?;
+    let text = state
+        .data_base
+        .data_base
+        .get(&key)
+        .ok_or_else(|| anyhow::format_err!("no text provided"))?;
+    let Some(before_cursor) = text_before_cursor(text, params.text_document_position.position)
+    else {
+        return Ok(None);
+    };
+    let items = match completion_context(before_cursor) {
+        Some(CompletionContext::Pass) => pass_completions(),
+        Some(CompletionContext::Operator) => operator_completions(),
+        None => return Ok(None),
+    };
+    Ok(Some(lsp_types::CompletionResponse::Array(items)))
+}
+
+/// The text on the line before `position`, which has the column in UTF-16 code units
+fn text_before_cursor(text: &str, position: lsp_types::Position) -> Option<&str> {
+    let line = text.lines().nth(position.line as usize)?;
+    let position = ksp_cfg_formatter::parser::Position::from_lsp(position, text);
+    let end = line
+        .char_indices()
+        .nth(position.col as usize - 1)
+        .map_or(line.len(), |(i, _)| i);
+    Some(&line[..end])
+}
+
+/// What kind of completions to offer at the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompletionContext {
+    /// After a `:` following a node identifier, where passes, `HAS` and `NEEDS` blocks can be written
+    Pass,
+    /// At the start of an identifier, where an operator can be written
+    Operator,
+}
+
+/// Finds what kind of completions to offer, based on the text on the line before the cursor
+fn completion_context(before_cursor: &str) -> Option<CompletionContext> {
+    let token = before_cursor.trim_start();
+    if token.is_empty() {
+        return Some(CompletionContext::Operator);
+    }
+    // Values and comments can contain anything, so don't offer completions there
+    if token.contains(['=', ' ', '\t', '{', '}']) || token.contains("//") {
+        return None;
+    }
+    if token.ends_with(':') && !token.ends_with("::") {
+        return Some(CompletionContext::Pass);
+    }
+    None
+}
+
+/// Completions for what can follow a `:` after a node identifier. The `:` is already typed, so it is not included
+fn pass_completions() -> Vec<lsp_types::CompletionItem> {
+    [
+        (
+            "HAS",
+            "HAS[$0]",
+            "Only apply the patch to nodes matching the checks",
+        ),
+        (
+            "NEEDS",
+            "NEEDS[$0]",
+            "Only apply the patch if the mods are installed",
+        ),
+        ("FIRST", "FIRST", "Run the patch in the first pass"),
+        (
+            "BEFORE",
+            "BEFORE[$0]",
+            "Run the patch before the mod's pass",
+        ),
+        ("FOR", "FOR[$0]", "Run the patch in the mod's pass"),
+        ("AFTER", "AFTER[$0]", "Run the patch after the mod's pass"),
+        (
+            "LAST",
+            "LAST[$0]",
+            "Run the patch in the last pass of the mod",
+        ),
+        ("FINAL", "FINAL", "Run the patch in the final pass"),
+    ]
+    .into_iter()
+    .map(|(label, snippet, detail)| lsp_types::CompletionItem {
+        label: label.to_owned(),
+        kind: Some(lsp_types::CompletionItemKind::KEYWORD),
+        detail: Some(detail.to_owned()),
+        insert_text: Some(snippet.to_owned()),
+        insert_text_format: Some(lsp_types::InsertTextFormat::SNIPPET),
+        ..Default::default()
+    })
+    .collect()
+}
+
+/// Completions for the operators that can be written at the start of an identifier
+fn operator_completions() -> Vec<lsp_types::CompletionItem> {
+    [
+        ("@", "Edit an existing node/variable"),
+        ("!", "Delete a node/variable"),
+        ("+", "Copy an existing node/variable"),
+        ("-", "Delete a node/variable"),
+        ("%", "Edit a node/variable, creating it if missing"),
+        ("$", "Copy an existing node/variable"),
+    ]
+    .into_iter()
+    .map(|(label, detail)| lsp_types::CompletionItem {
+        label: label.to_owned(),
+        kind: Some(lsp_types::CompletionItemKind::OPERATOR),
+        detail: Some(detail.to_owned()),
+        ..Default::default()
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_changed_line() {
+        let original = "node\n{\n\tkey = val\n  other = val\n}\n";
+        let new = "node\n{\n\tkey = val\n\tother = val\n}\n";
+        let edits = text_edits_from_diff(original, new).unwrap();
+        assert_eq!(
+            edits,
+            vec![lsp_types::TextEdit {
+                range: lsp_types::Range::new(
+                    lsp_types::Position::new(3, 0),
+                    lsp_types::Position::new(4, 0)
+                ),
+                new_text: "\tother = val\n".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unchanged_text() {
+        let text = "node\n{\n\tkey = val\n}\n";
+        assert!(text_edits_from_diff(text, text).unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_final_newline() {
+        let original = "node\n{\n}";
+        let new = "node\n{\n}\n";
+        let edits = text_edits_from_diff(original, new).unwrap();
+        assert_eq!(
+            edits,
+            vec![lsp_types::TextEdit {
+                range: lsp_types::Range::new(
+                    lsp_types::Position::new(2, 0),
+                    lsp_types::Position::new(2, 1)
+                ),
+                new_text: "}\n".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_final_newline_after_non_ascii() {
+        // `🚀` is two UTF-16 code units
+        let original = "node\n{\n}🚀";
+        let new = "node\n{\n}\n";
+        let edits = text_edits_from_diff(original, new).unwrap();
+        assert_eq!(
+            edits,
+            vec![lsp_types::TextEdit {
+                range: lsp_types::Range::new(
+                    lsp_types::Position::new(2, 0),
+                    lsp_types::Position::new(2, 3)
+                ),
+                new_text: "}\n".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn before_cursor_after_non_ascii() {
+        // `🚀` is two UTF-16 code units
+        let text = "node\n{\n\t🚀:\n}\n";
+        assert_eq!(
+            text_before_cursor(text, lsp_types::Position::new(2, 4)),
+            Some("\t🚀:")
+        );
+        assert_eq!(
+            text_before_cursor(text, lsp_types::Position::new(2, 3)),
+            Some("\t🚀")
+        );
+        assert_eq!(
+            text_before_cursor(text, lsp_types::Position::new(9, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn formatting_respects_collapse_setting() {
+        let options = lsp_types::FormattingOptions {
+            tab_size: 4,
+            insert_spaces: false,
+            ..Default::default()
+        };
+        let collapsed = "node { key = val }\n";
+        let expanded = "node\n{\n\tkey = val\n}\n";
+        for text in [collapsed, expanded] {
+            assert_eq!(
+                formatter(&options, Some(true)).format_text(text).unwrap(),
+                collapsed
+            );
+            assert_eq!(
+                formatter(&options, Some(false)).format_text(text).unwrap(),
+                expanded
+            );
+            assert_eq!(formatter(&options, None).format_text(text).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn rename_node_type() {
+        let text =
+            "@PART[*]\n{\n\tPART {}\n\t@MODULE { *../PART/key = 1 }\n\tPARTS {}\n}\nPART {}\n";
+        let edits = rename_edits(text, lsp_types::Position::new(6, 2), "MYPART").unwrap();
+        assert_eq!(edits.len(), 4);
+        assert!(edits.iter().all(|edit| edit.new_text == "MYPART"));
+        assert!(edits.contains(&lsp_types::TextEdit {
+            range: lsp_types::Range::new(
+                lsp_types::Position::new(0, 1),
+                lsp_types::Position::new(0, 5)
+            ),
+            new_text: "MYPART".to_owned(),
+        }));
+    }
+
+    #[test]
+    fn rename_outside_identifier() {
+        let text = "PART\n{\n\tkey = val\n}\n";
+        assert!(rename_edits(text, lsp_types::Position::new(2, 3), "MYPART").is_none());
+    }
+
+    #[test]
+    fn colon_triggers_pass_completions() {
+        assert_eq!(
+            completion_context("@PART[*]:"),
+            Some(CompletionContext::Pass)
+        );
+        assert_eq!(
+            completion_context("\t@MODULE:HAS[#name[x]]:"),
+            Some(CompletionContext::Pass)
+        );
+        assert_eq!(completion_context("\t"), Some(CompletionContext::Operator));
+        assert_eq!(completion_context("\tkey = a:"), None);
+        assert_eq!(completion_context("// note:"), None);
+        assert_eq!(completion_context("@PART"), None);
+    }
+
+    #[test]
+    fn diagnostics_are_cached_per_version() {
+        let mut state = State::new(crossbeam_channel::unbounded().0);
+        let uri = lsp_types::Url::parse("file:///tmp/cached.cfg").unwrap();
+        let key = uri.to_file_path().unwrap();
+        state
+            .data_base
+            .add_document_to_db(&lsp_types::DidOpenTextDocumentParams {
+                text_document: lsp_types::TextDocumentItem::new(
+                    uri.clone(),
+                    "ksp-cfg".to_owned(),
+                    1,
+                    "node {\n".to_owned(),
+                ),
+            })
+            .unwrap();
+        let parses = std::cell::Cell::new(0);
+        let compute = |text: &str| {
+            parses.set(parses.get() + 1);
+            document_diagnostics(text, &uri)
+        };
+        let first = cached_diagnostics(&mut state, key.clone(), compute).unwrap();
+        let second = cached_diagnostics(&mut state, key.clone(), compute).unwrap();
+        assert_eq!(parses.get(), 1);
+        assert_eq!(first, second);
+        assert!(!first.is_empty());
+
+        // A new version of the document is parsed again
+        state
+            .data_base
+            .update_document_in_db(&lsp_types::DidChangeTextDocumentParams {
+                text_document: lsp_types::VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+                content_changes: vec![lsp_types::TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: "node {}\n".to_owned(),
+                }],
+            })
+            .unwrap();
+        let third = cached_diagnostics(&mut state, key, compute).unwrap();
+        assert_eq!(parses.get(), 2);
+        assert!(third.is_empty());
+    }
 }
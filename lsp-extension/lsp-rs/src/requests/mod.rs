@@ -19,6 +19,8 @@ impl<'a> RequestDispatch<'a> {
             .handle_request::<reqs::DocumentDiagnosticRequest>(
                 handlers::handle_diagnostics_request,
             )?
+            .handle_request::<reqs::Rename>(handlers::handle_rename_request)?
+            .handle_request::<reqs::Completion>(handlers::handle_completion_request)?
             .finish();
         Ok(())
     }
@@ -30,8 +32,8 @@ impl<'a> RequestDispatch<'a> {
         R: lsp_types::request::Request,
         R::Params: serde::de::DeserializeOwned,
     {
-        let Some(req) = self.request.take()else {
-            return Ok(self)
+        let Some(req) = self.request.take() else {
+            return Ok(self);
         };
         let (id, params) = match req.extract::<R::Params>(R::METHOD) {
             Ok(it) => it,
@@ -11,17 +11,10 @@ pub const fn sev_to_sev(severity: &Severity) -> DiagnosticSeverity {
     }
 }
 
-// Notice the ´-1´s to get correct 0-indexed position in VSCode
-pub(crate) fn range_to_range(parser_range: ksp_cfg_formatter::parser::Range) -> lsp_types::Range {
-    lsp_types::Range::new(
-        lsp_types::Position::new(parser_range.start.line - 1, parser_range.start.col - 1),
-        lsp_types::Position::new(parser_range.end.line - 1, parser_range.end.col - 1),
-    )
-}
-
-pub fn diag_to_diag(val: &Diagnostic) -> lsp_types::Diagnostic {
+/// Converts a diagnostic for `text` to an LSP diagnostic
+pub fn diag_to_diag(val: &Diagnostic, text: &str) -> lsp_types::Diagnostic {
     lsp_types::Diagnostic {
-        range: range_to_range(val.range),
+        range: val.range.to_lsp(text),
         severity: val
             .severity
             .clone()
@@ -31,7 +24,7 @@ pub fn diag_to_diag(val: &Diagnostic) -> lsp_types::Diagnostic {
         related_information: val.related_information.as_ref().map(|v| {
             v.clone()
                 .into_iter()
-                .map(relinfo_to_relinfo)
+                .map(|info| relinfo_to_relinfo(info, text))
                 .filter_map(std::result::Result::ok)
                 .collect()
         }),
@@ -41,11 +34,12 @@ pub fn diag_to_diag(val: &Diagnostic) -> lsp_types::Diagnostic {
 
 pub fn relinfo_to_relinfo(
     value: RelatedInformation,
+    text: &str,
 ) -> Result<lsp_types::DiagnosticRelatedInformation, ()> {
     Ok(lsp_types::DiagnosticRelatedInformation {
         location: lsp_types::Location {
             uri: value.location.url.ok_or(())?,
-            range: crate::utils::range_to_range(value.location.range),
+            range: value.location.range.to_lsp(text),
         },
         message: value.message,
     })